@@ -1,12 +1,11 @@
-use simple_raytracer::camera::Camera;
-use simple_raytracer::light::LightSource;
+use simple_raytracer::camera::{Camera, Projection};
+use simple_raytracer::light::PointLight;
 use simple_raytracer::materials::*;
 use simple_raytracer::math::vector::Vec3D;
 use simple_raytracer::objects::mesh::Mesh;
 use simple_raytracer::objects::plane::Plane;
 use simple_raytracer::scene::*;
 
-use image;
 use palette::LinSrgb;
 use std::sync::Arc;
 
@@ -17,12 +16,14 @@ fn main() {
         k_d: 0.2,
         k_a: 0.8,
         alpha: 700.0,
+        ..PhongModel::new()
     };
     let mut aloevera = Mesh::from_file("assets/objects/aloevera.obj").unwrap();
     aloevera.set_phong_data(diffuse_grey);
-    let light = LightSource {
+    let light = PointLight {
         position: Vec3D::new(-100.0, 100.0, -100.0),
         color: LinSrgb::new(1.0, 1.0, 1.0),
+        intensity: 30000.0,
     };
     let floor = Plane {
         normal: Vec3D::new(0.0, 1.0, 0.0),
@@ -33,17 +34,25 @@ fn main() {
             k_d: (0.2),
             k_a: (0.15),
             alpha: (500.0),
+            ..PhongModel::new()
         }),
     };
     let my_scene = Scene {
         objects: vec![Arc::new(aloevera), Arc::new(floor)],
-        light_sources: vec![light],
+        light_sources: vec![Arc::new(light)],
         max_depth: 1,
+        sky: None,
+        area_light_samples: 0,
+        volumes: vec![],
     };
     let my_camera = Camera {
         origin: Vec3D::new(0.0, 0.4, -0.8),
         look_at: Vec3D::new(0.0, 0.25, 0.0),
         up: Vec3D::new(0.0, 1.0, 0.0),
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+        projection: Projection::Perspective,
+        stereo: None,
     };
     let renderer = Renderer {
         scene: my_scene,
@@ -51,6 +60,7 @@ fn main() {
         height: 1080,
         camera: my_camera,
         h_fov: f64::to_radians(70.0),
+        samples_per_pixel: 1,
     };
     // Capture scene as pixel array
     let pixels = renderer.render_scene();
@@ -62,5 +72,5 @@ fn main() {
     image::RgbImage::from_vec(renderer.width, renderer.height, pixels)
         .unwrap()
         .save(format!("{path}/{name}.png").as_str())
-        .expect(format!("Could not create file {name}.png").as_str());
+        .unwrap_or_else(|_| panic!("Could not create file {name}.png"));
 }
@@ -1,11 +1,10 @@
-use simple_raytracer::camera::Camera;
-use simple_raytracer::light::LightSource;
+use simple_raytracer::camera::{Camera, Projection};
+use simple_raytracer::light::PointLight;
 use simple_raytracer::materials::*;
 use simple_raytracer::math::vector::Vec3D;
 use simple_raytracer::objects::{plane::Plane, sphere::Sphere};
 use simple_raytracer::scene::*;
 
-use image;
 use palette::LinSrgb;
 use std::sync::Arc;
 
@@ -21,6 +20,7 @@ fn main() {
             k_d: (0.002),
             k_a: (0.01),
             alpha: (700.0),
+            ..PhongModel::new()
         }),
     };
     let earth = Sphere {
@@ -32,6 +32,7 @@ fn main() {
             k_d: (0.8),
             k_a: (0.02),
             alpha: (700.0),
+            ..PhongModel::new()
         }),
     };
     let floor = Plane {
@@ -43,6 +44,7 @@ fn main() {
             k_d: (0.90),
             k_a: (0.02),
             alpha: (100.0),
+            ..PhongModel::new()
         }),
     };
     let right_wall = Plane {
@@ -54,6 +56,7 @@ fn main() {
             k_d: (0.9),
             k_a: (0.1),
             alpha: (500.0),
+            ..PhongModel::new()
         }),
     };
     let left_wall = Plane {
@@ -65,6 +68,7 @@ fn main() {
             k_d: (0.9),
             k_a: (0.1),
             alpha: (500.0),
+            ..PhongModel::new()
         }),
     };
     let back_wall = Plane {
@@ -76,11 +80,13 @@ fn main() {
             k_d: (0.5),
             k_a: (0.1),
             alpha: (500.0),
+            ..PhongModel::new()
         }),
     };
-    let light = LightSource {
+    let light = PointLight {
         position: Vec3D::new(3.0, 100.0, -30.0),
         color: LinSrgb::new(1.0, 1.0, 1.0),
+        intensity: 11000.0,
     };
     let my_scene = Scene {
         objects: vec![
@@ -91,13 +97,20 @@ fn main() {
             Arc::new(left_wall),
             Arc::new(back_wall),
         ],
-        light_sources: vec![light],
+        light_sources: vec![Arc::new(light)],
         max_depth: 3,
+        sky: None,
+        area_light_samples: 0,
+        volumes: vec![],
     };
     let my_camera = Camera {
         origin: Vec3D::new(0.0, 3.0, -5.0),
         look_at: Vec3D::new(0.0, 1.0, 0.0),
         up: Vec3D::new(0.0, 1.0, 0.0),
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+        projection: Projection::Perspective,
+        stereo: None,
     };
     let renderer = Renderer {
         scene: my_scene,
@@ -105,6 +118,7 @@ fn main() {
         height: 1080,
         camera: my_camera,
         h_fov: f64::to_radians(90.0),
+        samples_per_pixel: 1,
     };
     // Capture scene as pixel array
     println!("here");
@@ -117,5 +131,5 @@ fn main() {
     image::RgbImage::from_vec(renderer.width, renderer.height, pixels)
         .unwrap()
         .save(format!("{path}/{name}.png").as_str())
-        .expect(format!("Could not create file {name}.png").as_str());
+        .unwrap_or_else(|_| panic!("Could not create file {name}.png"));
 }
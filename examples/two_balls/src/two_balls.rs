@@ -1,11 +1,10 @@
-use simple_raytracer::camera::Camera;
-use simple_raytracer::light::LightSource;
+use simple_raytracer::camera::{Camera, Projection};
+use simple_raytracer::light::PointLight;
 use simple_raytracer::materials::*;
 use simple_raytracer::math::vector::Vec3D;
 use simple_raytracer::objects::{plane::Plane, sphere::Sphere};
 use simple_raytracer::scene::*;
 
-use image;
 use palette::LinSrgb;
 use std::sync::Arc;
 
@@ -19,6 +18,7 @@ fn main() {
             k_d: (0.2),
             k_a: (0.1),
             alpha: (500.0),
+            ..PhongModel::new()
         }),
     };
     let ball2 = Sphere {
@@ -30,6 +30,7 @@ fn main() {
             k_d: (0.2),
             k_a: (0.3),
             alpha: (800.0),
+            ..PhongModel::new()
         }),
     };
     let floor = Plane {
@@ -41,6 +42,7 @@ fn main() {
             k_d: (0.95),
             k_a: (0.1),
             alpha: (5.0),
+            ..PhongModel::new()
         }),
     };
     let right_wall = Plane {
@@ -52,6 +54,7 @@ fn main() {
             k_d: (0.8),
             k_a: (0.1),
             alpha: (5.0),
+            ..PhongModel::new()
         }),
     };
     let left_wall = Plane {
@@ -63,6 +66,7 @@ fn main() {
             k_d: (0.8),
             k_a: (0.1),
             alpha: (5.0),
+            ..PhongModel::new()
         }),
     };
     let back_wall = Plane {
@@ -74,11 +78,13 @@ fn main() {
             k_d: (0.8),
             k_a: (0.1),
             alpha: (5.0),
+            ..PhongModel::new()
         }),
     };
-    let light = LightSource {
+    let light = PointLight {
         position: Vec3D::new(0.0, 20.0, 3.0),
         color: LinSrgb::new(1.0, 1.0, 1.0),
+        intensity: 400.0,
     };
     let my_scene = Scene {
         objects: vec![
@@ -89,13 +95,20 @@ fn main() {
             Arc::new(left_wall),
             Arc::new(back_wall),
         ],
-        light_sources: vec![light],
+        light_sources: vec![Arc::new(light)],
         max_depth: 3,
+        sky: None,
+        area_light_samples: 0,
+        volumes: vec![],
     };
     let my_camera = Camera {
         origin: Vec3D::new(0.0, 3.0, -5.0),
         look_at: Vec3D::new(0.0, 1.0, 0.0),
         up: Vec3D::new(0.0, 1.0, 0.0),
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+        projection: Projection::Perspective,
+        stereo: None,
     };
     let renderer = Renderer {
         scene: my_scene,
@@ -103,6 +116,7 @@ fn main() {
         height: 1080,
         camera: my_camera,
         h_fov: f64::to_radians(90.0),
+        samples_per_pixel: 1,
     };
     // Capture scene as pixel array
     let pixels = renderer.render_scene();
@@ -118,11 +132,11 @@ fn main() {
         renderer.height,
         &pixels,
     )
-    .expect(format!("Could not create file {name}.ppm").as_str());
+    .unwrap_or_else(|_| panic!("Could not create file {name}.ppm"));
 
     // Save pixel array as png
     image::RgbImage::from_vec(renderer.width, renderer.height, pixels)
         .unwrap()
         .save(format!("{path}/{name}.png").as_str())
-        .expect(format!("Could not create file {name}.png").as_str());
+        .unwrap_or_else(|_| panic!("Could not create file {name}.png"));
 }
@@ -0,0 +1,286 @@
+use crate::light::{DirectionalLight, Light, LightSample, PointLight, SpotLight};
+use crate::math::vector::Vec3D;
+use crate::scene::Renderer;
+use crate::utils;
+
+use palette::LinSrgb;
+
+// Values that can be blended between keys
+pub trait Interpolate: Copy {
+    fn add(self, other: Self) -> Self;
+    fn scale(self, factor: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn scale(self, factor: f64) -> Self {
+        self * factor
+    }
+}
+
+impl Interpolate for f32 {
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn scale(self, factor: f64) -> Self {
+        self * factor as f32
+    }
+}
+
+impl Interpolate for Vec3D {
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn scale(self, factor: f64) -> Self {
+        factor * self
+    }
+}
+
+impl Interpolate for LinSrgb {
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn scale(self, factor: f64) -> Self {
+        self * factor as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    CatmullRom, // smooth spline through the keys
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Key<T> {
+    pub time: f64,
+    pub value: T,
+}
+
+// Value changing over time. Before the first and after the last key the value is held.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    pub keys: Vec<Key<T>>, // sorted by time
+    pub interpolation: Interpolation,
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new(interpolation: Interpolation, keys: &[(f64, T)]) -> Self {
+        let mut keys: Vec<Key<T>> = keys
+            .iter()
+            .map(|&(time, value)| Key { time, value })
+            .collect();
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Track {
+            keys,
+            interpolation,
+        }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Track::new(Interpolation::Linear, &[(0.0, value)])
+    }
+
+    // Returns None if the track has no keys
+    pub fn value_at(&self, time: f64) -> Option<T> {
+        let next = self.keys.partition_point(|key| key.time <= time);
+        if next == 0 || next == self.keys.len() {
+            return self
+                .keys
+                .get(next.min(self.keys.len().saturating_sub(1)))
+                .map(|key| key.value);
+        }
+        let (a, b) = (self.keys[next - 1], self.keys[next]);
+        let s = (time - a.time) / (b.time - a.time);
+        Some(match self.interpolation {
+            Interpolation::Linear => a.value.scale(1.0 - s).add(b.value.scale(s)),
+            Interpolation::CatmullRom => {
+                // the Hermite segment written in Bezier form
+                let [p0, p1, p2, p3] = self.bezier_segment(next - 1);
+                let t = 1.0 - s;
+                p0.scale(t * t * t)
+                    .add(p1.scale(3.0 * t * t * s))
+                    .add(p2.scale(3.0 * t * s * s))
+                    .add(p3.scale(s * s * s))
+            }
+        })
+    }
+
+    // Points whose convex hull contains every value of the track
+    pub fn control_points(&self) -> Vec<T> {
+        match self.interpolation {
+            Interpolation::Linear => self.keys.iter().map(|key| key.value).collect(),
+            Interpolation::CatmullRom => (0..self.keys.len().saturating_sub(1))
+                .flat_map(|i| self.bezier_segment(i))
+                .chain(self.keys.iter().map(|key| key.value))
+                .collect(),
+        }
+    }
+
+    // Slope at a key, from the neighbouring keys. Keys at the ends use their only neighbour.
+    fn slope(&self, i: usize) -> T {
+        let a = self.keys[i.saturating_sub(1)];
+        let b = self.keys[(i + 1).min(self.keys.len() - 1)];
+        b.value
+            .add(a.value.scale(-1.0))
+            .scale(1.0 / (b.time - a.time))
+    }
+
+    // Bezier control points of the spline between key i and i + 1
+    fn bezier_segment(&self, i: usize) -> [T; 4] {
+        let (a, b) = (self.keys[i], self.keys[i + 1]);
+        let dt = b.time - a.time;
+        [
+            a.value,
+            a.value.add(self.slope(i).scale(dt / 3.0)),
+            b.value.add(self.slope(i + 1).scale(-dt / 3.0)),
+            b.value,
+        ]
+    }
+}
+
+// Tracks for the renderer's camera, parameters without a track stay as they are
+#[derive(Debug, Clone, Default)]
+pub struct CameraAnimation {
+    pub origin: Option<Track<Vec3D>>,
+    pub look_at: Option<Track<Vec3D>>,
+    pub h_fov: Option<Track<f64>>,
+}
+
+// Tracks for the parameters of a light. Which of them are used depends on the kind of light,
+// intensity is the irradiance of directional lights.
+#[derive(Debug, Clone, Default)]
+pub struct LightTracks {
+    pub position: Option<Track<Vec3D>>,
+    pub direction: Option<Track<Vec3D>>,
+    pub color: Option<Track<LinSrgb>>,
+    pub intensity: Option<Track<f32>>,
+}
+
+// Lights whose parameters can be driven by tracks
+pub trait AnimatableLight: Light + Clone {
+    fn animate(&mut self, tracks: &LightTracks, time: f64);
+}
+
+fn set<T: Interpolate>(parameter: &mut T, track: &Option<Track<T>>, time: f64) {
+    if let Some(value) = track.as_ref().and_then(|track| track.value_at(time)) {
+        *parameter = value;
+    }
+}
+
+impl AnimatableLight for PointLight {
+    fn animate(&mut self, tracks: &LightTracks, time: f64) {
+        set(&mut self.position, &tracks.position, time);
+        set(&mut self.color, &tracks.color, time);
+        set(&mut self.intensity, &tracks.intensity, time);
+    }
+}
+
+impl AnimatableLight for DirectionalLight {
+    fn animate(&mut self, tracks: &LightTracks, time: f64) {
+        set(&mut self.direction, &tracks.direction, time);
+        set(&mut self.color, &tracks.color, time);
+        set(&mut self.irradiance, &tracks.intensity, time);
+    }
+}
+
+impl AnimatableLight for SpotLight {
+    fn animate(&mut self, tracks: &LightTracks, time: f64) {
+        set(&mut self.position, &tracks.position, time);
+        set(&mut self.direction, &tracks.direction, time);
+        set(&mut self.color, &tracks.color, time);
+        set(&mut self.intensity, &tracks.intensity, time);
+    }
+}
+
+// A light with animated parameters, evaluated at the time of each ray
+#[derive(Debug, Clone)]
+pub struct AnimatedLight<L: AnimatableLight> {
+    pub light: L,
+    pub tracks: LightTracks,
+}
+
+impl<L: AnimatableLight> AnimatedLight<L> {
+    pub fn at(&self, time: f64) -> L {
+        let mut light = self.light.clone();
+        light.animate(&self.tracks, time);
+        light
+    }
+}
+
+impl<L: AnimatableLight> Light for AnimatedLight<L> {
+    fn sample(&self, point: Vec3D, time: f64) -> Option<LightSample> {
+        self.at(time).sample(point, time)
+    }
+
+    fn color(&self, time: f64) -> LinSrgb {
+        self.at(time).color(time)
+    }
+}
+
+// Renders the frames in the range at fps frames per unit of scene time. Every run of '#' in the
+// filename is replaced by the zero padded frame number, e.g. "turntable_###.ppm". The camera's
+// shutter interval is relative to the start of each frame, and the renderer is left as it was.
+pub fn render_frames(
+    renderer: &mut Renderer,
+    animation: &CameraAnimation,
+    frames: std::ops::Range<u32>,
+    fps: f64,
+    filename: &str,
+) -> Result<(), std::io::Error> {
+    let (origin, look_at, h_fov) = (
+        renderer.camera.origin,
+        renderer.camera.look_at,
+        renderer.h_fov,
+    );
+    let shutter = (renderer.camera.shutter_open, renderer.camera.shutter_close);
+    let mut result = Ok(());
+    for frame in frames {
+        let time = frame as f64 / fps;
+        set(&mut renderer.camera.origin, &animation.origin, time);
+        set(&mut renderer.camera.look_at, &animation.look_at, time);
+        set(&mut renderer.h_fov, &animation.h_fov, time);
+        renderer.camera.shutter_open = time + shutter.0;
+        renderer.camera.shutter_close = time + shutter.1;
+        let pixels = renderer.render_scene();
+        result = utils::save_ppm(
+            &frame_filename(filename, frame),
+            renderer.width,
+            renderer.height,
+            &pixels,
+        );
+        if result.is_err() {
+            break;
+        }
+    }
+    (
+        renderer.camera.origin,
+        renderer.camera.look_at,
+        renderer.h_fov,
+    ) = (origin, look_at, h_fov);
+    (renderer.camera.shutter_open, renderer.camera.shutter_close) = shutter;
+    result
+}
+
+pub fn frame_filename(pattern: &str, frame: u32) -> String {
+    let mut filename = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '#' {
+            filename.push(c);
+            continue;
+        }
+        let mut width = 1;
+        while chars.next_if_eq(&'#').is_some() {
+            width += 1;
+        }
+        filename += &format!("{frame:0width$}");
+    }
+    filename
+}
@@ -1,7 +1,73 @@
 use crate::math::vector::Vec3D;
 
+use rand::{rngs::SmallRng, Rng};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FisheyeMapping {
+    Equidistant, // distance from the image center proportional to the angle from the view axis
+    Equisolid,   // equal areas in the image cover equal solid angles
+}
+
+// Image layouts of the panoramic projections: equirectangular images cover all directions
+// with the view direction in the center, cubemaps are strips of six square faces along the
+// world axes in the order +x, -x, +y, -y, +z, -z. Fisheye images reach the renderer's
+// horizontal field of view at their left and right edges, which may exceed 180 degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,                      // with the renderer's horizontal field of view
+    Orthographic { view_width: f64 }, // parallel rays from a view of the given width
+    Equirectangular,
+    Cubemap,
+    Fisheye(FisheyeMapping),
+}
+
+// Direction through a point on a cubemap face, s and t going from -1 to 1 rightwards and
+// downwards. The faces are oriented like OpenGL cubemaps.
+pub fn cubemap_direction(face: usize, s: f64, t: f64) -> Vec3D {
+    match face {
+        0 => Vec3D::new(1.0, -t, -s),
+        1 => Vec3D::new(-1.0, -t, s),
+        2 => Vec3D::new(s, 1.0, t),
+        3 => Vec3D::new(s, -1.0, -t),
+        4 => Vec3D::new(s, -t, 1.0),
+        _ => Vec3D::new(-s, -t, -1.0),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    SideBySide, // left eye on the left half of the image
+    TopBottom,  // left eye on the top half of the image
+}
+
+// Two views from eyes interocular_distance apart, combined into one image. The eyes' rays
+// through the same pixel meet at convergence_distance along the view axis, or never if it is
+// infinite. Equirectangular and cubemap panoramas are rendered as omnidirectional stereo,
+// with the eyes offset sideways from every ray's horizontal direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stereo {
+    pub layout: StereoLayout,
+    pub interocular_distance: f64,
+    pub convergence_distance: f64,
+}
+
 pub struct Camera {
     pub origin: Vec3D,
     pub look_at: Vec3D,
     pub up: Vec3D,
+    // rays are sent out at random times while the shutter is open, blurring moving objects
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    pub projection: Projection,
+    pub stereo: Option<Stereo>,
+}
+
+impl Camera {
+    pub fn sample_time(&self, rng: &mut SmallRng) -> f64 {
+        if self.shutter_close > self.shutter_open {
+            self.shutter_open + rng.gen::<f64>() * (self.shutter_close - self.shutter_open)
+        } else {
+            self.shutter_open
+        }
+    }
 }
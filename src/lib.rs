@@ -1,10 +1,13 @@
 #[macro_use]
 extern crate impl_ops;
 
+pub mod animation;
 pub mod camera;
 pub mod light;
 pub mod materials;
 pub mod math;
+pub mod media;
 pub mod objects;
 pub mod scene;
+pub mod sky;
 pub mod utils;
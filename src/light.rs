@@ -2,7 +2,119 @@ use crate::math::vector::Vec3D;
 
 use palette::LinSrgb;
 
-pub struct LightSource {
+// Incoming light at a shaded point
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    pub direction: Vec3D,  // unit vector from the shaded point towards the light
+    pub distance: f64,     // distance to the light, infinite for directional lights
+    pub radiance: LinSrgb, // light arriving at the shaded point, attenuation included
+}
+
+pub trait Light {
+    // Returns None if the point receives no light at all, e.g. outside of a spot light's cone.
+    // time is the time of the ray that hit the point, for lights that change over time.
+    fn sample(&self, point: Vec3D, time: f64) -> Option<LightSample>;
+
+    // Color of the emitted light, used for the scene's ambient term
+    fn color(&self, time: f64) -> LinSrgb;
+}
+
+fn scale(color: LinSrgb, factor: f32) -> LinSrgb {
+    LinSrgb::new(
+        color.red * factor,
+        color.green * factor,
+        color.blue * factor,
+    )
+}
+
+// Point light with inverse square falloff. The intensity is given in W/sr.
+#[derive(Debug, Clone)]
+pub struct PointLight {
     pub position: Vec3D,
     pub color: LinSrgb,
+    pub intensity: f32,
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: Vec3D, _time: f64) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance = to_light.norm();
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            radiance: scale(self.color, self.intensity / (distance * distance) as f32),
+        })
+    }
+
+    fn color(&self, _time: f64) -> LinSrgb {
+        self.color
+    }
+}
+
+// Infinitely far away light such as the sun. The direction is the one the light travels in,
+// the irradiance is given in W/m^2.
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    pub direction: Vec3D,
+    pub color: LinSrgb,
+    pub irradiance: f32,
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: Vec3D, _time: f64) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction.unit_vector(),
+            distance: f64::INFINITY,
+            radiance: scale(self.color, self.irradiance),
+        })
+    }
+
+    fn color(&self, _time: f64) -> LinSrgb {
+        self.color
+    }
+}
+
+// Point light restricted to a cone around direction. cone_angle is the half angle of the cone,
+// the intensity fades out smoothly over the outermost falloff radians of the cone.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub position: Vec3D,
+    pub direction: Vec3D,
+    pub color: LinSrgb,
+    pub intensity: f32,
+    pub cone_angle: f64,
+    pub falloff: f64,
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: Vec3D, _time: f64) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance = to_light.norm();
+        let direction = to_light / distance;
+        let cos_angle = -direction * self.direction.unit_vector();
+        let cos_outer = self.cone_angle.cos();
+        if cos_angle <= cos_outer {
+            return None;
+        }
+        let cos_inner = f64::cos((self.cone_angle - self.falloff).max(0.0));
+        let cone_factor = if cos_angle >= cos_inner {
+            1.0
+        } else {
+            // smoothstep between the outer and inner cone
+            let x = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+            x * x * (3.0 - 2.0 * x)
+        };
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: scale(
+                self.color,
+                self.intensity * cone_factor as f32 / (distance * distance) as f32,
+            ),
+        })
+    }
+
+    fn color(&self, _time: f64) -> LinSrgb {
+        self.color
+    }
 }
@@ -1,5 +1,9 @@
+use crate::math::sampling::{cosine_hemisphere, phong_lobe};
+use crate::math::vector::Vec3D;
+
 use image::io::Reader as ImageReader;
-use palette::LinSrgb;
+use palette::{ComponentWise, LinSrgb, LinSrgba};
+use rand::{rngs::SmallRng, Rng};
 use std::error::Error;
 use std::path::Path;
 
@@ -13,13 +17,99 @@ pub enum Material {
 impl Material {
     pub fn get_color_at(&self, u: f64, v: f64) -> LinSrgb {
         match self {
-            Material::Color(color) => color.clone(),
+            Material::Color(color) => *color,
             Material::Texture(texture) => texture.get_color_at(u, v),
             Material::None => LinSrgb::new(0.0, 0.0, 0.0),
         }
     }
 }
 
+// Light entering the surface scatters around inside the object before leaving it again, found
+// with a random walk. Meant for closed objects such as spheres and closed meshes.
+#[derive(Debug, Clone)]
+pub struct Subsurface {
+    pub albedo: LinSrgb,     // fraction of light surviving each scattering event
+    pub mean_free_path: f64, // average distance between scattering events
+    pub max_steps: u32,      // walks taking longer are considered absorbed
+}
+
+// Marschner's model of light scattering in hair fibers, the sum of three lobes: R reflected off
+// the surface of the fiber, TT transmitted through it and TRT reflected off its back side. The
+// color of the material tints light passing through the fiber, once for TT and twice for TRT.
+// Angles are in radians.
+#[derive(Debug, Clone)]
+pub struct Hair {
+    pub ior: f64,                 // about 1.55 for human hair
+    pub tilt: f64,                // of the cuticle scales, shifts the highlights, about -0.05
+    pub roughness: f64,           // longitudinal width of the highlights, 0.05 to 0.2
+    pub azimuthal_roughness: f64, // width of the TT lobe around the forward direction
+}
+
+// Normalized gaussian with standard deviation width
+fn gaussian(width: f64, x: f64) -> f64 {
+    f64::exp(-x * x / (2.0 * width * width)) / (width * f64::sqrt(2.0 * std::f64::consts::PI))
+}
+
+// Fraction of unpolarized light reflected by a dielectric with relative index of refraction eta
+fn fresnel(eta: f64, cos_i: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = f64::sqrt(1.0 - sin2_t);
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+impl Hair {
+    // Like PhongModel::eval, with the tangent along the fiber instead of a normal. Lights are
+    // scaled like in the Phong model, which leaves out the 1/pi of a diffuse BRDF, so the
+    // result is scaled up by pi.
+    pub fn eval(
+        &self,
+        fiber_color: LinSrgb,
+        tangent: Vec3D,
+        to_light: Vec3D,
+        to_viewer: Vec3D,
+    ) -> LinSrgb {
+        use std::f64::consts::PI;
+        // longitudinal angles from the normal plane of the fiber
+        let theta_i = f64::asin((to_light * tangent).clamp(-1.0, 1.0));
+        let theta_o = f64::asin((to_viewer * tangent).clamp(-1.0, 1.0));
+        let theta_h = 0.5 * (theta_i + theta_o);
+        let cos_d = f64::cos(0.5 * (theta_o - theta_i));
+        // azimuthal angle between both directions around the fiber, in [0, pi]
+        let (light_perp, viewer_perp) = (to_light.perp(tangent), to_viewer.perp(tangent));
+        let phi = if light_perp.almost_zero() || viewer_perp.almost_zero() {
+            0.0
+        } else {
+            f64::acos((light_perp.unit_vector() * viewer_perp.unit_vector()).clamp(-1.0, 1.0))
+        };
+
+        let (alpha, beta) = (self.tilt, self.roughness);
+        let m_r = gaussian(beta, theta_h - alpha);
+        let m_tt = gaussian(0.5 * beta, theta_h + 0.5 * alpha);
+        let m_trt = gaussian(2.0 * beta, theta_h + 1.5 * alpha);
+
+        // Bravais' index for the projection of the light path onto the normal plane
+        let eta = f64::sqrt(self.ior * self.ior - 1.0 + cos_d * cos_d) / cos_d;
+        let cos_half_phi = f64::cos(0.5 * phi);
+        let reflected = fresnel(eta, cos_d * cos_half_phi);
+        let entering = 1.0 - fresnel(eta, cos_d);
+        let n_r = 0.25 * cos_half_phi * reflected;
+        let n_tt = entering * entering * gaussian(self.azimuthal_roughness, PI - phi);
+        let n_trt = 0.25 * cos_half_phi * entering * entering * fresnel(eta, cos_d);
+
+        let scale = PI * f64::cos(theta_i) / (cos_d * cos_d);
+        let white = (m_r * n_r * scale) as f32;
+        let tinted = (m_tt * n_tt * scale) as f32;
+        let tinted_twice = (m_trt * n_trt * scale) as f32;
+        fiber_color.component_wise_self(|c| white + c * tinted + c * c * tinted_twice)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PhongModel {
     pub material: Material,
@@ -27,6 +117,24 @@ pub struct PhongModel {
     pub k_d: f32,
     pub k_a: f32,
     pub alpha: f32,
+    pub emission: Material,
+    pub emission_strength: f32,
+    pub k_t: f32, // transmission, light passing through is tinted by the material
+    pub ior: f64, // index of refraction of transmissive materials
+    pub absorption: LinSrgb, // Beer-Lambert absorption coefficients inside transmissive materials
+    // surfaces are cut out where the alpha channel of the opacity texture is below alpha_threshold
+    pub opacity: Option<Texture>,
+    pub alpha_threshold: f32,
+    // replaces the diffuse component of the Phong model
+    pub subsurface: Option<Subsurface>,
+    // replaces the Phong model on objects with fibers, like curves
+    pub hair: Option<Hair>,
+}
+
+impl Default for PhongModel {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PhongModel {
@@ -37,42 +145,179 @@ impl PhongModel {
             k_d: 0.0,
             k_a: 0.0,
             alpha: 0.0,
+            emission: Material::None,
+            emission_strength: 0.0,
+            k_t: 0.0,
+            ior: 1.0,
+            absorption: LinSrgb::new(0.0, 0.0, 0.0),
+            opacity: None,
+            alpha_threshold: 0.5,
+            subsurface: None,
+            hair: None,
         }
     }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission_strength > 0.0 && !matches!(self.emission, Material::None)
+    }
+
+    pub fn get_emission_at(&self, u: f64, v: f64) -> LinSrgb {
+        self.emission.get_color_at(u, v) * self.emission_strength
+    }
+
+    // True if the surface is cut out at (u, v) and should not be hit by any ray
+    pub fn is_cut_out(&self, u: f64, v: f64) -> bool {
+        match &self.opacity {
+            Some(opacity) => opacity.get_alpha_at(u, v) < self.alpha_threshold,
+            None => false,
+        }
+    }
+
+    pub fn is_transmissive(&self) -> bool {
+        self.k_t > 0.0
+    }
+
+    // Filter applied to light entering the material
+    pub fn get_transmission_at(&self, u: f64, v: f64) -> LinSrgb {
+        self.material.get_color_at(u, v) * self.k_t
+    }
+
+    // Fraction of light left after traveling distance through the material
+    pub fn get_absorption_over(&self, distance: f64) -> LinSrgb {
+        let distance = distance as f32;
+        self.absorption
+            .component_wise_self(|sigma| f32::exp(-sigma * distance))
+    }
+
+    // Fraction of the light arriving from to_light that is scattered towards to_viewer,
+    // cosine term included. All directions are unit vectors pointing away from the surface.
+    pub fn eval(
+        &self,
+        object_color: LinSrgb,
+        normal: Vec3D,
+        to_light: Vec3D,
+        to_viewer: Vec3D,
+    ) -> LinSrgb {
+        let dot_diffuse = normal * to_light;
+        if dot_diffuse <= 0.0 {
+            return LinSrgb::new(0.0, 0.0, 0.0);
+        }
+        let dot_specular = (-to_light).reflect(normal) * to_viewer;
+        let specular_component = if dot_specular > 0.0 {
+            dot_specular.powf(self.alpha as f64) as f32
+        } else {
+            0.0
+        };
+        let diffuse_component = dot_diffuse as f32;
+        object_color.component_wise_self(|a| a * diffuse_component * self.k_d)
+            + LinSrgb::new(1.0, 1.0, 1.0) * (specular_component * self.k_s)
+    }
+
+    // Probability of picking the specular lobe when sampling a direction
+    fn specular_probability(&self) -> f64 {
+        if self.k_s + self.k_d > 0.0 {
+            (self.k_s / (self.k_s + self.k_d)) as f64
+        } else {
+            0.0
+        }
+    }
+
+    // Samples a direction towards which light is gathered, picking either the diffuse or the
+    // specular lobe. Returns None if the model doesn't scatter any light.
+    pub fn sample_direction(
+        &self,
+        rng: &mut SmallRng,
+        normal: Vec3D,
+        to_viewer: Vec3D,
+    ) -> Option<Vec3D> {
+        if self.k_s + self.k_d <= 0.0 {
+            return None;
+        }
+        if rng.gen::<f64>() < self.specular_probability() {
+            let mirror = (-to_viewer).reflect(normal);
+            Some(phong_lobe(rng, mirror, self.alpha as f64))
+        } else {
+            Some(cosine_hemisphere(rng, normal))
+        }
+    }
+
+    // Solid angle density of sample_direction returning to_light
+    pub fn pdf(&self, normal: Vec3D, to_light: Vec3D, to_viewer: Vec3D) -> f64 {
+        use std::f64::consts::PI;
+        let cos_theta = normal * to_light;
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+        let p_specular = self.specular_probability();
+        let cos_mirror = (-to_viewer).reflect(normal) * to_light;
+        let pdf_specular = if cos_mirror > 0.0 {
+            (self.alpha as f64 + 1.0) / (2.0 * PI) * cos_mirror.powf(self.alpha as f64)
+        } else {
+            0.0
+        };
+        p_specular * pdf_specular + (1.0 - p_specular) * cos_theta / PI
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Texture {
     pub width: usize,
     pub height: usize,
-    pixel_colors: Vec<LinSrgb>,
+    pixel_colors: Vec<LinSrgba>,
 }
 
 impl Texture {
     pub fn load_texture<P: AsRef<Path>>(filename: P) -> Result<Self, Box<dyn Error>> {
-        let texture = ImageReader::open(filename)?.decode()?.to_rgb8();
+        let texture = ImageReader::open(filename)?.decode()?.to_rgba8();
         let w = texture.width() as usize;
         let h = texture.height() as usize;
         let buffer = texture.into_vec();
-        assert_eq!((3 * w * h) as usize, buffer.len());
-        let mut pixel_colors: Vec<LinSrgb> = Vec::new();
-        pixel_colors.reserve((w * h) as usize);
-        (0..(w * h) as usize).into_iter().for_each(|idx| {
-            let r = buffer[3 * idx] as f32;
-            let g = buffer[3 * idx + 1] as f32;
-            let b = buffer[3 * idx + 2] as f32;
-            pixel_colors.push(LinSrgb::new(r / 255.0, g / 255.0, b / 255.0));
+        assert_eq!(4 * w * h, buffer.len());
+        let mut pixel_colors: Vec<LinSrgba> = Vec::with_capacity(w * h);
+        (0..(w * h)).for_each(|idx| {
+            let r = buffer[4 * idx] as f32;
+            let g = buffer[4 * idx + 1] as f32;
+            let b = buffer[4 * idx + 2] as f32;
+            let a = buffer[4 * idx + 3] as f32;
+            pixel_colors.push(LinSrgba::new(r / 255.0, g / 255.0, b / 255.0, a / 255.0));
         });
         Ok(Texture {
             width: w,
             height: h,
-            pixel_colors: pixel_colors,
+            pixel_colors,
         })
     }
 
+    // Nearest texel, coordinates outside of [0, 1) wrap around
+    fn get_texel_at(&self, u: f64, v: f64) -> LinSrgba {
+        let x = (u.rem_euclid(1.0) * (self.width as f64)) as usize;
+        let y = (v.rem_euclid(1.0) * (self.height as f64)) as usize;
+        self.pixel_colors[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+    }
+
     pub fn get_color_at(&self, u: f64, v: f64) -> LinSrgb {
-        let x = (u * (self.width as f64)) as usize;
-        let y = (v * (self.height as f64)) as usize;
-        self.pixel_colors[y * self.width + x]
+        self.get_texel_at(u, v).color
+    }
+
+    pub fn get_alpha_at(&self, u: f64, v: f64) -> f32 {
+        self.get_texel_at(u, v).alpha
+    }
+
+    // Brightness in [0, 1] interpolated bilinearly between texel centers, for height maps
+    pub fn get_height_at(&self, u: f64, v: f64) -> f64 {
+        let x = u.rem_euclid(1.0) * self.width as f64 - 0.5;
+        let y = v.rem_euclid(1.0) * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let brightness = |dx: f64, dy: f64| {
+            let texel = self.get_texel_at(
+                (x0 + dx + 0.5) / self.width as f64,
+                (y0 + dy + 0.5) / self.height as f64,
+            );
+            (texel.red + texel.green + texel.blue) as f64 / 3.0
+        };
+        let top = brightness(0.0, 0.0) * (1.0 - fx) + brightness(1.0, 0.0) * fx;
+        let bottom = brightness(0.0, 1.0) * (1.0 - fx) + brightness(1.0, 1.0) * fx;
+        top * (1.0 - fy) + bottom * fy
     }
 }
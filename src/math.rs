@@ -1 +1,3 @@
+pub mod polynomial;
+pub mod sampling;
 pub mod vector;
@@ -0,0 +1,110 @@
+// Real roots of polynomials up to degree four, in ascending order
+
+// a * x^2 + b * x + c = 0
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    // avoids cancellation when b^2 >> 4ac
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (x1, x2) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    if x1 < x2 {
+        vec![x1, x2]
+    } else {
+        vec![x2, x1]
+    }
+}
+
+// a * x^3 + b * x^2 + c * x + d = 0
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    use std::f64::consts::PI;
+    if a.abs() < 1e-12 {
+        return solve_quadratic(b, c, d);
+    }
+    let (b, c, d) = (b / a, c / a, d / a);
+    // depressed cubic t^3 + p * t + q with x = t - b / 3
+    let shift = b / 3.0;
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+    let mut roots = if discriminant > 0.0 {
+        let sqrt_discriminant = discriminant.sqrt();
+        let u = (-q / 2.0 + sqrt_discriminant).cbrt();
+        let v = (-q / 2.0 - sqrt_discriminant).cbrt();
+        vec![u + v - shift]
+    } else if p.abs() < 1e-14 {
+        vec![-shift]
+    } else {
+        // three real roots, trigonometric solution
+        let r = f64::sqrt(-p / 3.0);
+        let phi = f64::acos((3.0 * q / (2.0 * p) / r).clamp(-1.0, 1.0)) / 3.0;
+        (0..3)
+            .map(|k| 2.0 * r * f64::cos(phi - 2.0 * PI * k as f64 / 3.0) - shift)
+            .collect()
+    };
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots
+}
+
+// a * x^4 + b * x^3 + c * x^2 + d * x + e = 0, solved with Ferrari's method and polished
+// with a few Newton iterations
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a.abs() < 1e-12 {
+        return solve_cubic(b, c, d, e);
+    }
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    // depressed quartic y^4 + p * y^2 + q * y + r with x = y - b / 4
+    let shift = b / 4.0;
+    let b2 = b * b;
+    let p = c - 3.0 * b2 / 8.0;
+    let q = d - b * c / 2.0 + b2 * b / 8.0;
+    let r = e - b * d / 4.0 + b2 * c / 16.0 - 3.0 * b2 * b2 / 256.0;
+
+    let mut roots: Vec<f64> = Vec::new();
+    if q.abs() < 1e-12 {
+        // biquadratic
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                roots.push(z.sqrt());
+                roots.push(-z.sqrt());
+            }
+        }
+    } else {
+        // a positive root of the resolvent cubic splits the quartic into two quadratics
+        let m = solve_cubic(1.0, p, p * p / 4.0 - r, -q * q / 8.0)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if m <= 0.0 {
+            return vec![];
+        }
+        let s = f64::sqrt(2.0 * m);
+        roots.extend(solve_quadratic(1.0, -s, p / 2.0 + m + q / (2.0 * s)));
+        roots.extend(solve_quadratic(1.0, s, p / 2.0 + m - q / (2.0 * s)));
+    }
+
+    let f = |x: f64| (((x + b) * x + c) * x + d) * x + e;
+    let df = |x: f64| ((4.0 * x + 3.0 * b) * x + 2.0 * c) * x + d;
+    let mut roots: Vec<f64> = roots
+        .into_iter()
+        .map(|y| {
+            let mut x = y - shift;
+            for _ in 0..3 {
+                let slope = df(x);
+                if slope.abs() < 1e-14 {
+                    break;
+                }
+                x -= f(x) / slope;
+            }
+            x
+        })
+        .collect();
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots
+}
@@ -0,0 +1,72 @@
+use crate::math::vector::Vec3D;
+
+use rand::Rng;
+use std::f64::consts::PI;
+
+// Builds two unit vectors that together with normal form an orthonormal basis
+pub fn orthonormal_basis(normal: Vec3D) -> (Vec3D, Vec3D) {
+    let helper = if normal.x.abs() > 0.9 {
+        Vec3D::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3D::new(1.0, 0.0, 0.0)
+    };
+    let tangent = Vec3D::cross(normal, helper).unit_vector();
+    let bitangent = Vec3D::cross(normal, tangent);
+    (tangent, bitangent)
+}
+
+// Uniformly distributed direction on the unit sphere, pdf = 1 / (4 pi)
+pub fn uniform_sphere<R: Rng>(rng: &mut R) -> Vec3D {
+    let z: f64 = 1.0 - 2.0 * rng.gen::<f64>();
+    let r = f64::sqrt((1.0 - z * z).max(0.0));
+    let phi = 2.0 * PI * rng.gen::<f64>();
+    Vec3D::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// Uniformly distributed direction on the hemisphere around normal, pdf = 1 / (2 pi)
+pub fn uniform_hemisphere<R: Rng>(rng: &mut R, normal: Vec3D) -> Vec3D {
+    let direction = uniform_sphere(rng);
+    if direction * normal < 0.0 {
+        -direction
+    } else {
+        direction
+    }
+}
+
+// Uniformly distributed barycentric coordinates (u, v, 1 - u - v) on a triangle
+pub fn uniform_triangle<R: Rng>(rng: &mut R) -> (f64, f64) {
+    let sqrt_r1 = rng.gen::<f64>().sqrt();
+    let r2: f64 = rng.gen();
+    (1.0 - sqrt_r1, r2 * sqrt_r1)
+}
+
+// Cosine weighted direction on the hemisphere around normal, pdf = cos(theta) / pi
+pub fn cosine_hemisphere<R: Rng>(rng: &mut R, normal: Vec3D) -> Vec3D {
+    let r = rng.gen::<f64>().sqrt();
+    let phi = 2.0 * PI * rng.gen::<f64>();
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    let z = f64::sqrt((1.0 - r * r).max(0.0));
+    r * phi.cos() * tangent + r * phi.sin() * bitangent + z * normal
+}
+
+// Direction distributed around axis proportional to cos(theta)^exponent,
+// pdf = (exponent + 1) / (2 pi) * cos(theta)^exponent
+pub fn phong_lobe<R: Rng>(rng: &mut R, axis: Vec3D, exponent: f64) -> Vec3D {
+    let cos_theta = rng.gen::<f64>().powf(1.0 / (exponent + 1.0));
+    let sin_theta = f64::sqrt((1.0 - cos_theta * cos_theta).max(0.0));
+    let phi = 2.0 * PI * rng.gen::<f64>();
+    let (tangent, bitangent) = orthonormal_basis(axis);
+    sin_theta * phi.cos() * tangent + sin_theta * phi.sin() * bitangent + cos_theta * axis
+}
+
+// Multiple importance sampling weight of a sample taken with strategy f,
+// when n_f samples are taken from f and n_g samples from strategy g
+pub fn power_heuristic(n_f: u32, pdf_f: f64, n_g: u32, pdf_g: f64) -> f64 {
+    let f = n_f as f64 * pdf_f;
+    let g = n_g as f64 * pdf_g;
+    if f == 0.0 {
+        0.0
+    } else {
+        (f * f) / (f * f + g * g)
+    }
+}
@@ -63,7 +63,7 @@ impl Vec3D {
     }
 
     pub fn unit_vector(&self) -> Self {
-        let mut result = self.clone();
+        let mut result = *self;
         result.normalize();
         result
     }
@@ -97,6 +97,19 @@ impl Vec3D {
         self - 2.0 * self.project_onto(normal)
     }
 
+    // refracts incoming unit vector self through a surface with unit normal facing against it,
+    // eta = n_incoming / n_transmitted. Returns None on total internal reflection.
+    pub fn refract(self, normal: Vec3D, eta: f64) -> Option<Self> {
+        let cos_i = -(self * normal);
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            None
+        } else {
+            let cos_t = f64::sqrt(1.0 - sin2_t);
+            Some(eta * self + (eta * cos_i - cos_t) * normal)
+        }
+    }
+
     pub fn almost_equal(self, other: Vec3D) -> bool {
         (self - other).almost_zero()
     }
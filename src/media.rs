@@ -0,0 +1,300 @@
+use crate::math::{sampling::orthonormal_basis, vector::Vec3D};
+use crate::objects::{aabb::Aabb, hittables::Hittable, ray::Ray};
+
+use palette::LinSrgb;
+use rand::{rngs::SmallRng, Rng};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// Density values on a regular grid spanning the box from min to max, interpolated trilinearly.
+// values are stored x-major: values[(z * ny + y) * nx + x]
+#[derive(Debug, Clone)]
+pub struct DensityGrid {
+    pub min: Vec3D,
+    pub max: Vec3D,
+    resolution: (usize, usize, usize),
+    values: Vec<f64>,
+    max_value: f64,
+}
+
+impl DensityGrid {
+    pub fn new(
+        min: Vec3D,
+        max: Vec3D,
+        resolution: (usize, usize, usize),
+        values: Vec<f64>,
+    ) -> Self {
+        let (nx, ny, nz) = resolution;
+        assert!(nx > 0 && ny > 0 && nz > 0);
+        assert_eq!(nx * ny * nz, values.len());
+        let max_value = values.iter().cloned().fold(0.0, f64::max);
+        DensityGrid {
+            min,
+            max,
+            resolution,
+            values,
+            max_value,
+        }
+    }
+
+    // Samples density_fn at the voxel centers
+    pub fn from_fn<F: Fn(Vec3D) -> f64>(
+        min: Vec3D,
+        max: Vec3D,
+        resolution: (usize, usize, usize),
+        density_fn: F,
+    ) -> Self {
+        let (nx, ny, nz) = resolution;
+        let size = max - min;
+        let mut values = Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let point = min
+                        + Vec3D::new(
+                            size.x * (x as f64 + 0.5) / nx as f64,
+                            size.y * (y as f64 + 0.5) / ny as f64,
+                            size.z * (z as f64 + 0.5) / nz as f64,
+                        );
+                    values.push(density_fn(point));
+                }
+            }
+        }
+        DensityGrid::new(min, max, resolution, values)
+    }
+
+    fn value(&self, x: usize, y: usize, z: usize) -> f64 {
+        let (nx, ny, _) = self.resolution;
+        self.values[(z * ny + y) * nx + x]
+    }
+
+    // Density at point, zero outside of the grid
+    pub fn lookup(&self, point: Vec3D) -> f64 {
+        let (nx, ny, nz) = self.resolution;
+        let size = self.max - self.min;
+        let local = point - self.min;
+        if local.x < 0.0
+            || local.y < 0.0
+            || local.z < 0.0
+            || local.x > size.x
+            || local.y > size.y
+            || local.z > size.z
+        {
+            return 0.0;
+        }
+        // continuous voxel coordinates relative to the voxel centers
+        let to_voxel = |p: f64, s: f64, n: usize| -> (usize, usize, f64) {
+            let c = (p / s * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i0 = c.floor() as usize;
+            let i1 = (i0 + 1).min(n - 1);
+            (i0, i1, c - i0 as f64)
+        };
+        let (x0, x1, fx) = to_voxel(local.x, size.x, nx);
+        let (y0, y1, fy) = to_voxel(local.y, size.y, ny);
+        let (z0, z1, fz) = to_voxel(local.z, size.z, nz);
+        let lerp = |a: f64, b: f64, f: f64| a + (b - a) * f;
+        let c00 = lerp(self.value(x0, y0, z0), self.value(x1, y0, z0), fx);
+        let c10 = lerp(self.value(x0, y1, z0), self.value(x1, y1, z0), fx);
+        let c01 = lerp(self.value(x0, y0, z1), self.value(x1, y0, z1), fx);
+        let c11 = lerp(self.value(x0, y1, z1), self.value(x1, y1, z1), fx);
+        lerp(lerp(c00, c10, fy), lerp(c01, c11, fy), fz)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Density {
+    Homogeneous,
+    Grid(DensityGrid),
+}
+
+// Absorbing and scattering medium. The coefficients are given per unit length at density 1.
+#[derive(Debug, Clone)]
+pub struct Medium {
+    pub absorption: f64,
+    pub scattering: f64,
+    pub color: LinSrgb,  // tints the scattered light
+    pub anisotropy: f64, // Henyey-Greenstein g, > 0 scatters forward, < 0 backward
+    pub density: Density,
+}
+
+impl Medium {
+    pub fn homogeneous(absorption: f64, scattering: f64, color: LinSrgb, anisotropy: f64) -> Self {
+        Medium {
+            absorption,
+            scattering,
+            color,
+            anisotropy,
+            density: Density::Homogeneous,
+        }
+    }
+
+    pub fn extinction(&self) -> f64 {
+        self.absorption + self.scattering
+    }
+
+    // Fraction of the extinguished light that is scattered rather than absorbed
+    pub fn albedo(&self) -> f64 {
+        if self.extinction() > 0.0 {
+            self.scattering / self.extinction()
+        } else {
+            0.0
+        }
+    }
+
+    pub fn get_density_at(&self, point: Vec3D) -> f64 {
+        match &self.density {
+            Density::Homogeneous => 1.0,
+            Density::Grid(grid) => grid.lookup(point),
+        }
+    }
+
+    fn max_density(&self) -> f64 {
+        match &self.density {
+            Density::Homogeneous => 1.0,
+            Density::Grid(grid) => grid.max_value,
+        }
+    }
+
+    // Henyey-Greenstein phase function, cos_theta is the cosine between the propagation
+    // directions before and after scattering
+    pub fn phase(&self, cos_theta: f64) -> f64 {
+        let g = self.anisotropy;
+        let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+
+    // Samples a new propagation direction proportional to the phase function
+    pub fn sample_direction(&self, rng: &mut SmallRng, direction: Vec3D) -> Vec3D {
+        let g = self.anisotropy;
+        let xi: f64 = rng.gen();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        };
+        let sin_theta = f64::sqrt((1.0 - cos_theta * cos_theta).max(0.0));
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let (tangent, bitangent) = orthonormal_basis(direction);
+        sin_theta * phi.cos() * tangent + sin_theta * phi.sin() * bitangent + cos_theta * direction
+    }
+}
+
+// A medium filling the inside of a boundary object, or the whole scene if there is no boundary.
+// The boundary is only used to find where rays enter and leave the medium, it isn't rendered.
+pub struct Volume {
+    pub medium: Medium,
+    pub boundary: Option<Arc<dyn Hittable + Send + Sync>>,
+}
+
+impl Volume {
+    // Segments of the ray between t_min and t_max that lie inside the volume. Grid densities
+    // are zero outside of the grid, so the segments are clipped to its bounds.
+    fn get_intervals(&self, ray: Ray, t_min: f64, t_max: f64) -> Vec<(f64, f64)> {
+        let intervals = self.get_boundary_intervals(ray, t_min, t_max);
+        match &self.medium.density {
+            Density::Homogeneous => intervals,
+            Density::Grid(grid) => {
+                let bounds = Aabb::new(grid.min, grid.max);
+                intervals
+                    .into_iter()
+                    .filter_map(|(t_start, t_end)| bounds.clip(ray, t_start, t_end))
+                    .collect()
+            }
+        }
+    }
+
+    fn get_boundary_intervals(&self, ray: Ray, t_min: f64, t_max: f64) -> Vec<(f64, f64)> {
+        let boundary = match &self.boundary {
+            Some(boundary) => boundary,
+            None => return vec![(t_min, t_max)],
+        };
+        let mut intervals = Vec::new();
+        let mut entry: Option<f64> = None;
+        let mut t_start = t_min;
+        let mut first_hit = true;
+        // search past t_max, a ray might start inside of the boundary and end before leaving it
+        while let Some(hit) = boundary.intersect(ray, t_start, f64::INFINITY) {
+            if first_hit && !hit.front_face {
+                entry = Some(t_min);
+            }
+            first_hit = false;
+            if hit.t >= t_max {
+                break;
+            }
+            if hit.front_face {
+                entry = Some(hit.t);
+            } else if let Some(t_entry) = entry.take() {
+                intervals.push((t_entry, hit.t));
+            }
+            t_start = hit.t + 0.00001;
+        }
+        if let Some(t_entry) = entry {
+            intervals.push((t_entry, t_max));
+        }
+        intervals
+    }
+
+    // Distance along the ray to the next real collision with the medium, found by delta
+    // tracking. Returns None if the ray passes the segment between t_min and t_max unscattered.
+    pub fn sample_collision(
+        &self,
+        ray: Ray,
+        t_min: f64,
+        t_max: f64,
+        rng: &mut SmallRng,
+    ) -> Option<f64> {
+        let max_density = self.medium.max_density();
+        let majorant = self.medium.extinction() * max_density;
+        if majorant <= 0.0 {
+            return None;
+        }
+        let scale = ray.direction.norm();
+        for (t_start, t_end) in self.get_intervals(ray, t_min, t_max) {
+            let mut t = t_start;
+            loop {
+                t -= f64::ln(1.0 - rng.gen::<f64>()) / (majorant * scale);
+                if t >= t_end {
+                    break;
+                }
+                if rng.gen::<f64>() * max_density < self.medium.get_density_at(ray.at(t)) {
+                    return Some(t);
+                }
+            }
+        }
+        None
+    }
+
+    // Fraction of light passing along the ray between t_min and t_max. Homogeneous media are
+    // evaluated analytically, heterogeneous ones with ratio tracking.
+    pub fn transmittance(&self, ray: Ray, t_min: f64, t_max: f64, rng: &mut SmallRng) -> f64 {
+        let max_density = self.medium.max_density();
+        let majorant = self.medium.extinction() * max_density;
+        if majorant <= 0.0 {
+            return 1.0;
+        }
+        let scale = ray.direction.norm();
+        let mut transmittance = 1.0;
+        for (t_start, t_end) in self.get_intervals(ray, t_min, t_max) {
+            match self.medium.density {
+                Density::Homogeneous => {
+                    transmittance *= f64::exp(-majorant * scale * (t_end - t_start));
+                }
+                Density::Grid(_) => {
+                    let mut t = t_start;
+                    loop {
+                        t -= f64::ln(1.0 - rng.gen::<f64>()) / (majorant * scale);
+                        if t >= t_end {
+                            break;
+                        }
+                        transmittance *= 1.0 - self.medium.get_density_at(ray.at(t)) / max_density;
+                        if transmittance <= 0.0 {
+                            return 0.0;
+                        }
+                    }
+                }
+            }
+        }
+        transmittance
+    }
+}
@@ -1,6 +1,22 @@
+pub mod aabb;
+pub mod bezier;
+pub mod boxes;
+pub mod capsule;
+pub mod cone;
+pub mod csg;
+pub mod curve;
+pub mod cylinder;
+pub mod disk;
+pub mod displacement;
+pub mod heightfield;
 pub mod hittables;
 pub mod mesh;
+pub mod motion;
 pub mod plane;
+pub mod quad;
 pub mod ray;
+pub mod sdf;
 pub mod sphere;
+pub mod subdivision;
+pub mod torus;
 pub mod triangle;
@@ -0,0 +1,118 @@
+use crate::math::vector::Vec3D;
+use crate::objects::ray::Ray;
+
+// Axis-aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3D,
+    pub max: Vec3D,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3D, max: Vec3D) -> Self {
+        Aabb { min, max }
+    }
+
+    // Smallest box containing all points, None if there are none
+    pub fn from_points<I: IntoIterator<Item = Vec3D>>(points: I) -> Option<Self> {
+        points.into_iter().fold(None, |aabb: Option<Aabb>, point| {
+            Some(match aabb {
+                Some(aabb) => aabb.grow(point),
+                None => Aabb::new(point, point),
+            })
+        })
+    }
+
+    pub fn grow(self, point: Vec3D) -> Self {
+        Aabb {
+            min: Vec3D::new(
+                self.min.x.min(point.x),
+                self.min.y.min(point.y),
+                self.min.z.min(point.z),
+            ),
+            max: Vec3D::new(
+                self.max.x.max(point.x),
+                self.max.y.max(point.y),
+                self.max.z.max(point.z),
+            ),
+        }
+    }
+
+    pub fn union(self, other: Aabb) -> Self {
+        self.grow(other.min).grow(other.max)
+    }
+
+    // Overlap of both boxes, empty (min > max) if they don't overlap
+    pub fn intersection(self, other: Aabb) -> Self {
+        Aabb {
+            min: Vec3D::new(
+                self.min.x.max(other.min.x),
+                self.min.y.max(other.min.y),
+                self.min.z.max(other.min.z),
+            ),
+            max: Vec3D::new(
+                self.max.x.min(other.max.x),
+                self.max.y.min(other.max.y),
+                self.max.z.min(other.max.z),
+            ),
+        }
+    }
+
+    // Box extended by margin in every direction
+    pub fn pad(self, margin: Vec3D) -> Self {
+        Aabb {
+            min: self.min - margin,
+            max: self.max + margin,
+        }
+    }
+
+    pub fn center(&self) -> Vec3D {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn contains(&self, point: Vec3D) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
+
+    // Slab test, true if the ray passes through the box somewhere between t_min and t_max
+    pub fn hit(&self, ray: Ray, t_min: f64, t_max: f64) -> bool {
+        self.clip(ray, t_min, t_max).is_some()
+    }
+
+    // Part of the range from t_min to t_max where the ray is inside of the box
+    pub fn clip(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t_near = t_min;
+        let mut t_far = t_max;
+        for (origin, direction, min, max) in [
+            (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
+        ] {
+            let inv_direction = 1.0 / direction;
+            let mut t0 = (min - origin) * inv_direction;
+            let mut t1 = (max - origin) * inv_direction;
+            if inv_direction < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // f64::max / min ignore the NaN produced by rays lying in a slab boundary
+            t_near = t_near.max(t0);
+            t_far = t_far.min(t1);
+            if t_far < t_near {
+                return None;
+            }
+        }
+        Some((t_near, t_far))
+    }
+}
+
+// Bounds of a disk with the given center, unit normal and radius
+pub fn disk_bounds(center: Vec3D, normal: Vec3D, radius: f64) -> Aabb {
+    let extent = Vec3D::new(
+        radius * f64::sqrt((1.0 - normal.x * normal.x).max(0.0)),
+        radius * f64::sqrt((1.0 - normal.y * normal.y).max(0.0)),
+        radius * f64::sqrt((1.0 - normal.z * normal.z).max(0.0)),
+    );
+    Aabb::new(center - extent, center + extent)
+}
@@ -0,0 +1,175 @@
+use crate::math::vector::Vec3D;
+use crate::objects::{mesh::Mesh, triangle::Triangle};
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Bicubic Bezier patch, control_points[i][j] is the j-th point along u in the i-th row along v
+#[derive(Debug, Clone, Copy)]
+pub struct BezierPatch {
+    pub control_points: [[Vec3D; 4]; 4],
+}
+
+// Cubic Bernstein polynomials and their derivatives at t
+pub(crate) fn bernstein(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+}
+
+pub(crate) fn bernstein_derivative(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [
+        -3.0 * s * s,
+        3.0 * s * s - 6.0 * t * s,
+        6.0 * t * s - 3.0 * t * t,
+        3.0 * t * t,
+    ]
+}
+
+impl BezierPatch {
+    fn evaluate(&self, weights_u: [f64; 4], weights_v: [f64; 4]) -> Vec3D {
+        let mut point = Vec3D::default();
+        for (i, row) in self.control_points.iter().enumerate() {
+            for (j, control_point) in row.iter().enumerate() {
+                point += weights_v[i] * weights_u[j] * *control_point;
+            }
+        }
+        point
+    }
+
+    pub fn point_at(&self, u: f64, v: f64) -> Vec3D {
+        self.evaluate(bernstein(u), bernstein(v))
+    }
+
+    // dP/du x dP/dv. Collapsed rows of control points (like at the top of the teapot lid)
+    // make a derivative vanish, there the normal is taken slightly inside the patch instead.
+    pub fn get_normal_at(&self, u: f64, v: f64) -> Vec3D {
+        let normal_at = |u: f64, v: f64| {
+            let du = self.evaluate(bernstein_derivative(u), bernstein(v));
+            let dv = self.evaluate(bernstein(u), bernstein_derivative(v));
+            du.cross(dv)
+        };
+        let normal = normal_at(u, v);
+        if normal.norm2() > 1e-20 {
+            return normal.unit_vector();
+        }
+        normal_at(u.clamp(1e-3, 1.0 - 1e-3), v.clamp(1e-3, 1.0 - 1e-3)).unit_vector()
+    }
+
+    // Number of segments along u and v so the tessellated surface stays within tolerance
+    // of the patch, using the bound 3/4 * max |P[k-1] - 2 P[k] + P[k+1]| / n^2 per curve.
+    // The errors along u and v add up, so each direction gets half of the tolerance.
+    pub fn divisions_for(&self, tolerance: f64) -> (usize, usize) {
+        let p = &self.control_points;
+        let mut flatness_u: f64 = 0.0;
+        let mut flatness_v: f64 = 0.0;
+        for row in p {
+            for k in 1..3 {
+                flatness_u = flatness_u.max((row[k - 1] - 2.0 * row[k] + row[k + 1]).norm());
+            }
+        }
+        for k in 1..3 {
+            for ((a, b), c) in p[k - 1].iter().zip(&p[k]).zip(&p[k + 1]) {
+                flatness_v = flatness_v.max((a - 2.0 * b + c).norm());
+            }
+        }
+        let divisions =
+            |flatness: f64| (f64::sqrt(1.5 * flatness / tolerance).ceil() as usize).max(1);
+        (divisions(flatness_u), divisions(flatness_v))
+    }
+
+    // Splits the patch into a grid of nu x nv quads of two triangles each, with the patch
+    // normals as vertex normals and the patch parameters as texture coordinates
+    pub fn tessellate(&self, nu: usize, nv: usize) -> Vec<Triangle> {
+        let vertex = |i: usize, j: usize| {
+            let (u, v) = (j as f64 / nu as f64, i as f64 / nv as f64);
+            // Triangle flips v like OBJ texture coordinates
+            (self.point_at(u, v), self.get_normal_at(u, v), (u, 1.0 - v))
+        };
+        let mut triangles = Vec::with_capacity(2 * nu * nv);
+        for i in 0..nv {
+            for j in 0..nu {
+                let corners = [
+                    vertex(i, j),
+                    vertex(i, j + 1),
+                    vertex(i + 1, j + 1),
+                    vertex(i + 1, j),
+                ];
+                for [a, b, c] in [[0, 1, 2], [0, 2, 3]] {
+                    let (a, b, c) = (corners[a], corners[b], corners[c]);
+                    let triangle = Triangle {
+                        vert_a: a.0,
+                        vert_b: b.0,
+                        vert_c: c.0,
+                        normal: Some([a.1, b.1, c.1]),
+                        uv: Some([a.2, b.2, c.2]),
+                    };
+                    // quads next to collapsed rows have a zero area half
+                    if triangle.area() > 0.0 {
+                        triangles.push(triangle);
+                    }
+                }
+            }
+        }
+        triangles
+    }
+
+    // Patches in Newell's .bpt format: the number of patches, then for every patch a line
+    // with its degrees ("3 3") followed by its 16 control points row by row
+    pub fn load_bpt<P: AsRef<Path>>(filename: P) -> Result<Vec<Self>, Box<dyn Error>> {
+        let contents = fs::read_to_string(filename)?;
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let numbers = |line: Option<&str>| -> Result<Vec<f64>, Box<dyn Error>> {
+            let line = line.ok_or("unexpected end of .bpt file")?;
+            line.split_whitespace()
+                .map(|n| n.parse::<f64>().map_err(|e| e.into()))
+                .collect()
+        };
+        let n_patches = numbers(lines.next())?
+            .first()
+            .copied()
+            .ok_or("missing patch count")? as usize;
+        let mut patches = Vec::with_capacity(n_patches);
+        for _ in 0..n_patches {
+            let degrees = numbers(lines.next())?;
+            if degrees != [3.0, 3.0] {
+                return Err(format!("only bicubic patches are supported, got {degrees:?}").into());
+            }
+            let mut control_points = [[Vec3D::default(); 4]; 4];
+            for row in control_points.iter_mut() {
+                for point in row.iter_mut() {
+                    let coords = numbers(lines.next())?;
+                    if coords.len() != 3 {
+                        return Err("control points need three coordinates".into());
+                    }
+                    *point = Vec3D::from_vec(coords);
+                }
+            }
+            patches.push(BezierPatch { control_points });
+        }
+        Ok(patches)
+    }
+}
+
+// Tessellates all patches with the same number of divisions along u and v, which keeps the
+// edges between neighbouring patches crack-free whichever way they are joined. The count is
+// the finest any patch needs to stay within tolerance.
+pub fn tessellate(patches: &[BezierPatch], tolerance: f64) -> Mesh {
+    let (nu, nv) = patches
+        .iter()
+        .map(|patch| patch.divisions_for(tolerance))
+        .fold((1, 1), |(nu, nv), (pu, pv)| (nu.max(pu), nv.max(pv)));
+    let divisions = nu.max(nv);
+    Mesh::new(
+        patches
+            .iter()
+            .flat_map(|patch| patch.tessellate(divisions, divisions))
+            .collect(),
+    )
+}
+
+// Loads the patches of a .bpt file and tessellates them into a mesh
+pub fn load_bpt_mesh<P: AsRef<Path>>(filename: P, tolerance: f64) -> Result<Mesh, Box<dyn Error>> {
+    Ok(tessellate(&BezierPatch::load_bpt(filename)?, tolerance))
+}
@@ -0,0 +1,154 @@
+use crate::materials::PhongModel;
+use crate::math::vector::Vec3D;
+use crate::objects::{aabb::Aabb, hittables::*, ray::Ray};
+
+// Entry and exit points of a ray (in box space) through the box from min to max.
+// Each face is uv mapped over its full extent.
+fn intersect_box(origin: Vec3D, direction: Vec3D, min: Vec3D, max: Vec3D) -> Vec<HitCandidate> {
+    let o = [origin.x, origin.y, origin.z];
+    let d = [direction.x, direction.y, direction.z];
+    let lo = [min.x, min.y, min.z];
+    let hi = [max.x, max.y, max.z];
+    let mut t_near = f64::NEG_INFINITY;
+    let mut t_far = f64::INFINITY;
+    let (mut near_axis, mut far_axis) = (0, 0);
+    for axis in 0..3 {
+        if d[axis] == 0.0 {
+            if o[axis] < lo[axis] || o[axis] > hi[axis] {
+                return vec![];
+            }
+            continue;
+        }
+        let t0 = (lo[axis] - o[axis]) / d[axis];
+        let t1 = (hi[axis] - o[axis]) / d[axis];
+        let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+        if t0 > t_near {
+            t_near = t0;
+            near_axis = axis;
+        }
+        if t1 < t_far {
+            t_far = t1;
+            far_axis = axis;
+        }
+    }
+    if t_near > t_far {
+        return vec![];
+    }
+    let face = |t: f64, axis: usize, sign: f64| {
+        let mut normal = [0.0; 3];
+        normal[axis] = sign;
+        let p = [o[0] + t * d[0], o[1] + t * d[1], o[2] + t * d[2]];
+        let (i, j) = ((axis + 1) % 3, (axis + 2) % 3);
+        HitCandidate {
+            t,
+            normal: Vec3D::new(normal[0], normal[1], normal[2]),
+            u: (p[i] - lo[i]) / (hi[i] - lo[i]),
+            v: (p[j] - lo[j]) / (hi[j] - lo[j]),
+        }
+    };
+    // the ray enters through a face it points against and leaves through one it points along
+    vec![
+        face(t_near, near_axis, -d[near_axis].signum()),
+        face(t_far, far_axis, d[far_axis].signum()),
+    ]
+}
+
+#[derive(Debug, Clone)]
+pub struct AxisAlignedBox {
+    pub min: Vec3D,
+    pub max: Vec3D,
+    pub phong_data: Option<PhongModel>,
+}
+
+impl Hittable for AxisAlignedBox {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = intersect_box(ray.origin, ray.direction, self.min, self.max);
+        closest_hit(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = intersect_box(ray.origin, ray.direction, self.min, self.max);
+        closest_crossing(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+}
+
+// Box centered at center with its edges along the orthonormal axes
+#[derive(Debug, Clone)]
+pub struct OrientedBox {
+    pub center: Vec3D,
+    pub axes: [Vec3D; 3],
+    pub half_extents: Vec3D,
+    pub phong_data: Option<PhongModel>,
+}
+
+impl OrientedBox {
+    // The third axis is x_axis x y_axis, y_axis is made orthogonal to x_axis
+    pub fn new(center: Vec3D, half_extents: Vec3D, x_axis: Vec3D, y_axis: Vec3D) -> Self {
+        let x_axis = x_axis.unit_vector();
+        let y_axis = y_axis.perp(x_axis).unit_vector();
+        OrientedBox {
+            center,
+            axes: [x_axis, y_axis, x_axis.cross(y_axis)],
+            half_extents,
+            phong_data: None,
+        }
+    }
+
+    fn to_local(&self, v: Vec3D) -> Vec3D {
+        Vec3D::new(v * self.axes[0], v * self.axes[1], v * self.axes[2])
+    }
+
+    fn to_world(&self, v: Vec3D) -> Vec3D {
+        v.x * self.axes[0] + v.y * self.axes[1] + v.z * self.axes[2]
+    }
+
+    // t is unchanged by the rigid transform into box space
+    fn candidates(&self, ray: Ray) -> Vec<HitCandidate> {
+        let mut candidates = intersect_box(
+            self.to_local(ray.origin - self.center),
+            self.to_local(ray.direction),
+            -self.half_extents,
+            self.half_extents,
+        );
+        for candidate in candidates.iter_mut() {
+            candidate.normal = self.to_world(candidate.normal);
+        }
+        candidates
+    }
+}
+
+impl Hittable for OrientedBox {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_hit(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_crossing(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let h = self.half_extents;
+        let extent = |i: usize| {
+            let component = |axis: Vec3D| [axis.x, axis.y, axis.z][i].abs();
+            h.x * component(self.axes[0])
+                + h.y * component(self.axes[1])
+                + h.z * component(self.axes[2])
+        };
+        let extent = Vec3D::new(extent(0), extent(1), extent(2));
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
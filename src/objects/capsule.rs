@@ -0,0 +1,88 @@
+use crate::materials::PhongModel;
+use crate::math::polynomial::solve_quadratic;
+use crate::math::sampling::orthonormal_basis;
+use crate::math::vector::Vec3D;
+use crate::objects::{aabb::Aabb, disk::polar_angle, hittables::*, ray::Ray};
+
+// All points within radius of the segment from start to end. u runs around the segment
+// and v along it, from the tip of the start cap to the tip of the end cap.
+#[derive(Debug, Clone)]
+pub struct Capsule {
+    pub start: Vec3D,
+    pub end: Vec3D,
+    pub radius: f64,
+    pub phong_data: Option<PhongModel>,
+}
+
+impl Capsule {
+    // Crossings of the ray with the surface, in any order and at any t
+    fn candidates(&self, ray: Ray) -> Vec<HitCandidate> {
+        let length = (self.end - self.start).norm();
+        let axis = (self.end - self.start) / length;
+        let (tangent, bitangent) = orthonormal_basis(axis);
+        let mut candidates = Vec::with_capacity(6);
+        let mut push = |t: f64| {
+            let p = ray.at(t) - self.start;
+            let y = p * axis;
+            // the normal points away from the closest point on the segment
+            let normal = (p - y.clamp(0.0, length) * axis) / self.radius;
+            candidates.push(HitCandidate {
+                t,
+                normal,
+                u: polar_angle(normal * tangent, normal * bitangent),
+                v: (y + self.radius) / (length + 2.0 * self.radius),
+            });
+        };
+
+        // body, the infinite cylinder restricted to the segment
+        let origin = ray.origin - self.start;
+        let (o_y, d_y) = (origin * axis, ray.direction * axis);
+        let o_perp = origin - o_y * axis;
+        let d_perp = ray.direction - d_y * axis;
+        for t in solve_quadratic(
+            d_perp.norm2(),
+            2.0 * (o_perp * d_perp),
+            o_perp.norm2() - self.radius * self.radius,
+        ) {
+            if (0.0..=length).contains(&(o_y + t * d_y)) {
+                push(t);
+            }
+        }
+
+        // hemispherical caps, each only where it lies past its end of the segment
+        for (center, outside) in [(self.start, -1.0), (self.end, 1.0)] {
+            let offset = ray.origin - center;
+            for t in solve_quadratic(
+                ray.direction.norm2(),
+                2.0 * (ray.direction * offset),
+                offset.norm2() - self.radius * self.radius,
+            ) {
+                if outside * ((ray.at(t) - center) * axis) > 0.0 {
+                    push(t);
+                }
+            }
+        }
+        candidates
+    }
+}
+
+impl Hittable for Capsule {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_hit(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_crossing(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let margin = Vec3D::new(self.radius, self.radius, self.radius);
+        Aabb::from_points([self.start, self.end]).map(|aabb| aabb.pad(margin))
+    }
+}
@@ -0,0 +1,119 @@
+use crate::materials::PhongModel;
+use crate::math::polynomial::solve_quadratic;
+use crate::math::sampling::orthonormal_basis;
+use crate::math::vector::Vec3D;
+use crate::objects::{
+    aabb::{disk_bounds, Aabb},
+    disk::polar_angle,
+    hittables::*,
+    ray::Ray,
+};
+
+// Closed cone from base along the unit axis, with the radius changing linearly from
+// base_radius to top_radius. A top_radius of zero gives a pointed cone, otherwise a frustum.
+// Mapped like the cylinder.
+#[derive(Debug, Clone)]
+pub struct Cone {
+    pub base: Vec3D,
+    pub axis: Vec3D,
+    pub base_radius: f64,
+    pub top_radius: f64,
+    pub height: f64,
+    pub phong_data: Option<PhongModel>,
+}
+
+impl Cone {
+    // Pointed cone with its base disk at base and the tip at apex
+    pub fn new(base: Vec3D, apex: Vec3D, radius: f64) -> Self {
+        Cone {
+            base,
+            axis: (apex - base).unit_vector(),
+            base_radius: radius,
+            top_radius: 0.0,
+            height: (apex - base).norm(),
+            phong_data: None,
+        }
+    }
+
+    // Crossings of the ray with the surface, in any order and at any t
+    fn candidates(&self, ray: Ray) -> Vec<HitCandidate> {
+        let origin = ray.origin - self.base;
+        let (tangent, bitangent) = orthonormal_basis(self.axis);
+        let (o_y, d_y) = (origin * self.axis, ray.direction * self.axis);
+        let o_perp = origin - o_y * self.axis;
+        let d_perp = ray.direction - d_y * self.axis;
+        let slope = (self.top_radius - self.base_radius) / self.height;
+        let mut candidates = Vec::with_capacity(4);
+
+        // side: |o_perp + t * d_perp|^2 = (r0 + slope * (o_y + t * d_y))^2
+        let r_o = self.base_radius + slope * o_y;
+        for t in solve_quadratic(
+            d_perp.norm2() - slope * slope * d_y * d_y,
+            2.0 * (o_perp * d_perp - slope * d_y * r_o),
+            o_perp.norm2() - r_o * r_o,
+        ) {
+            let y = o_y + t * d_y;
+            // the equation also describes the mirrored cone beyond the tip
+            if (0.0..=self.height).contains(&y) && self.base_radius + slope * y >= 0.0 {
+                let radial = o_perp + t * d_perp;
+                let normal = radial.unit_vector() - slope * self.axis;
+                candidates.push(HitCandidate {
+                    t,
+                    normal: normal.unit_vector(),
+                    u: polar_angle(radial * tangent, radial * bitangent),
+                    v: y / self.height,
+                });
+            }
+        }
+
+        // caps
+        if d_y != 0.0 {
+            for (y, radius, sign) in [
+                (0.0, self.base_radius, -1.0),
+                (self.height, self.top_radius, 1.0),
+            ] {
+                if radius <= 0.0 {
+                    continue;
+                }
+                let t = (y - o_y) / d_y;
+                let radial = o_perp + t * d_perp;
+                if radial.norm2() <= radius * radius {
+                    candidates.push(HitCandidate {
+                        t,
+                        normal: sign * self.axis,
+                        u: polar_angle(radial * tangent, radial * bitangent),
+                        v: radial.norm() / radius,
+                    });
+                }
+            }
+        }
+        candidates
+    }
+}
+
+impl Hittable for Cone {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_hit(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_crossing(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let top = self.base + self.height * self.axis;
+        Some(
+            disk_bounds(self.base, self.axis, self.base_radius).union(disk_bounds(
+                top,
+                self.axis,
+                self.top_radius,
+            )),
+        )
+    }
+}
@@ -0,0 +1,195 @@
+use crate::materials::PhongModel;
+use crate::math::vector::Vec3D;
+use crate::objects::{aabb::Aabb, hittables::*, ray::Ray};
+
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference, // left minus right
+}
+
+impl CsgOperation {
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+// Boolean combination of two closed objects (or the half-spaces behind planes).
+// Surfaces keep the material of the object they belong to, so the walls cut by a difference
+// show the material of the subtracted object, unless phong_data is set to override both.
+pub struct Csg {
+    pub operation: CsgOperation,
+    pub left: Arc<dyn Hittable + Send + Sync>,
+    pub right: Arc<dyn Hittable + Send + Sync>,
+    pub phong_data: Option<PhongModel>,
+}
+
+impl Csg {
+    pub fn union(
+        left: Arc<dyn Hittable + Send + Sync>,
+        right: Arc<dyn Hittable + Send + Sync>,
+    ) -> Self {
+        Csg {
+            operation: CsgOperation::Union,
+            left,
+            right,
+            phong_data: None,
+        }
+    }
+
+    pub fn intersection(
+        left: Arc<dyn Hittable + Send + Sync>,
+        right: Arc<dyn Hittable + Send + Sync>,
+    ) -> Self {
+        Csg {
+            operation: CsgOperation::Intersection,
+            left,
+            right,
+            phong_data: None,
+        }
+    }
+
+    pub fn difference(
+        left: Arc<dyn Hittable + Send + Sync>,
+        right: Arc<dyn Hittable + Send + Sync>,
+    ) -> Self {
+        Csg {
+            operation: CsgOperation::Difference,
+            left,
+            right,
+            phong_data: None,
+        }
+    }
+}
+
+// Walks the surface crossings of an object along a ray one at a time, including those on
+// cut out parts of the surface
+struct Crossings<'a> {
+    object: &'a (dyn Hittable + Send + Sync),
+    ray: Ray,
+    next: Option<IntersectionData<'a>>,
+}
+
+impl<'a> Crossings<'a> {
+    fn new(object: &'a (dyn Hittable + Send + Sync), ray: Ray, t_min: f64) -> Self {
+        Crossings {
+            object,
+            ray,
+            next: object.intersect_solid(ray, t_min, f64::INFINITY),
+        }
+    }
+
+    // The ray starts inside if the object contains its start, or else if the first crossing
+    // leaves the object
+    fn starts_inside(&self, t_min: f64) -> bool {
+        self.object
+            .contains(self.ray.at(t_min))
+            .unwrap_or_else(|| self.next.as_ref().is_some_and(|hit| !hit.front_face))
+    }
+
+    fn advance(&mut self) -> Option<IntersectionData<'a>> {
+        let hit = self.next.take()?;
+        self.next = self
+            .object
+            .intersect_solid(self.ray, hit.t + 0.00001, f64::INFINITY);
+        Some(hit)
+    }
+}
+
+impl Csg {
+    // Merges the crossings of both objects in order along the ray, tracking whether the ray is
+    // inside of each, and returns the first crossing where being inside of the result changes.
+    // Cut outs only apply to that crossing, skipped if cut_outs is set.
+    fn first_hit(
+        &self,
+        ray: Ray,
+        t_min: f64,
+        t_max: f64,
+        cut_outs: bool,
+    ) -> Option<IntersectionData<'_>> {
+        let mut left = Crossings::new(self.left.as_ref(), ray, t_min);
+        let mut right = Crossings::new(self.right.as_ref(), ray, t_min);
+        let mut in_left = left.starts_inside(t_min);
+        let mut in_right = right.starts_inside(t_min);
+        let mut inside = self.operation.contains(in_left, in_right);
+        loop {
+            let from_left = match (&left.next, &right.next) {
+                (Some(a), Some(b)) => a.t <= b.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => return None,
+            };
+            let hit = if from_left {
+                left.advance()?
+            } else {
+                right.advance()?
+            };
+            if hit.t >= t_max {
+                return None;
+            }
+            if from_left {
+                in_left = hit.front_face;
+            } else {
+                in_right = hit.front_face;
+            }
+            let now_inside = self.operation.contains(in_left, in_right);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+            let phong_data = self.phong_data.as_ref().or(hit.phong_data);
+            if cut_outs && phong_data.is_some_and(|phong_data| phong_data.is_cut_out(hit.u, hit.v))
+            {
+                continue;
+            }
+            // the normal already faces the ray, only the side of the combined solid can change,
+            // e.g. entering the subtracted object means leaving a difference
+            return Some(IntersectionData {
+                front_face: now_inside,
+                phong_data,
+                ..hit
+            });
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, true)
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, false)
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data
+            .as_ref()
+            .or_else(|| self.left.get_phong_data())
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let left = self.left.bounding_box();
+        let right = self.right.bounding_box();
+        match self.operation {
+            CsgOperation::Union => left.zip(right).map(|(a, b)| a.union(b)),
+            CsgOperation::Intersection => match (left, right) {
+                (Some(a), Some(b)) => Some(a.intersection(b)),
+                (a, b) => a.or(b),
+            },
+            CsgOperation::Difference => left,
+        }
+    }
+    fn contains(&self, point: Vec3D) -> Option<bool> {
+        let in_left = self.left.contains(point)?;
+        let in_right = self.right.contains(point)?;
+        Some(self.operation.contains(in_left, in_right))
+    }
+}
@@ -0,0 +1,478 @@
+use crate::materials::PhongModel;
+use crate::math::sampling::orthonormal_basis;
+use crate::math::vector::Vec3D;
+use crate::objects::bezier::{bernstein, bernstein_derivative};
+use crate::objects::{aabb::Aabb, hittables::*, ray::Ray};
+
+use std::error::Error;
+use std::fs;
+use std::ops::{Add, Mul};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveBasis {
+    Linear,  // straight segments between consecutive points
+    BSpline, // uniform cubic B-spline, smooth but not passing through the points
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveShape {
+    Ribbon,   // flat strip turned towards the ray, enough for thin fur and grass
+    Cylinder, // round tube
+}
+
+// A single hair or blade of grass, with one width per point
+#[derive(Debug, Clone, Default)]
+pub struct Strand {
+    pub points: Vec<Vec3D>,
+    pub widths: Vec<f64>,
+}
+
+// Part of a strand as a cubic Bezier curve, u_range is its share of the strand's u
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    points: [Vec3D; 4],
+    widths: [f64; 4],
+    u_range: (f64, f64),
+}
+
+// Rows give the Bezier control points as weights of the curve's points
+const LINEAR_TO_BEZIER: [[f64; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [2.0 / 3.0, 1.0 / 3.0, 0.0, 0.0],
+    [1.0 / 3.0, 2.0 / 3.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+];
+const BSPLINE_TO_BEZIER: [[f64; 4]; 4] = [
+    [1.0 / 6.0, 4.0 / 6.0, 1.0 / 6.0, 0.0],
+    [0.0, 4.0 / 6.0, 2.0 / 6.0, 0.0],
+    [0.0, 2.0 / 6.0, 4.0 / 6.0, 0.0],
+    [0.0, 1.0 / 6.0, 4.0 / 6.0, 1.0 / 6.0],
+];
+
+fn bezier_at<T: Copy + Add<Output = T> + Mul<f64, Output = T>>(p: [T; 4], weights: [f64; 4]) -> T {
+    p[0] * weights[0] + p[1] * weights[1] + p[2] * weights[2] + p[3] * weights[3]
+}
+
+// Both halves of a Bezier curve split at t = 0.5
+fn split_half<T: Copy + Add<Output = T> + Mul<f64, Output = T>>(p: [T; 4]) -> ([T; 4], [T; 4]) {
+    let mid = |a: T, b: T| (a + b) * 0.5;
+    let (p01, p12, p23) = (mid(p[0], p[1]), mid(p[1], p[2]), mid(p[2], p[3]));
+    let (p012, p123) = (mid(p01, p12), mid(p12, p23));
+    let center = mid(p012, p123);
+    ([p[0], p01, p012, center], [center, p123, p23, p[3]])
+}
+
+impl Strand {
+    fn segments(&self, basis: CurveBasis) -> Vec<Segment> {
+        assert_eq!(self.points.len(), self.widths.len());
+        let (window, matrix) = match basis {
+            CurveBasis::Linear => (2, LINEAR_TO_BEZIER),
+            CurveBasis::BSpline => (4, BSPLINE_TO_BEZIER),
+        };
+        if self.points.len() < window {
+            return vec![];
+        }
+        let n = self.points.len() - window + 1;
+        (0..n)
+            .map(|i| {
+                let mut points = [Vec3D::default(); 4];
+                let mut widths = [0.0; 4];
+                for (row, weights) in matrix.iter().enumerate() {
+                    for (k, weight) in weights.iter().enumerate().take(window) {
+                        points[row] += *weight * self.points[i + k];
+                        widths[row] += weight * self.widths[i + k];
+                    }
+                }
+                Segment {
+                    points,
+                    widths,
+                    u_range: (i as f64 / n as f64, (i + 1) as f64 / n as f64),
+                }
+            })
+            .collect()
+    }
+}
+
+// Bounding volume hierarchy over the segments, leaves hold a few segment indices
+#[derive(Debug, Clone)]
+enum Bvh {
+    Leaf(Aabb, Vec<usize>),
+    Node(Aabb, Box<Bvh>, Box<Bvh>),
+}
+
+impl Bvh {
+    // Splits the segments in half along the axis their centers are spread the most
+    fn build(mut indices: Vec<usize>, bounds: &[Aabb]) -> Bvh {
+        let aabb = indices
+            .iter()
+            .map(|&i| bounds[i])
+            .reduce(Aabb::union)
+            .unwrap();
+        if indices.len() <= 4 {
+            return Bvh::Leaf(aabb, indices);
+        }
+        let centers = Aabb::from_points(indices.iter().map(|&i| bounds[i].center())).unwrap();
+        let extent = centers.max - centers.min;
+        let coordinate = |p: Vec3D| {
+            if extent.x >= extent.y && extent.x >= extent.z {
+                p.x
+            } else if extent.y >= extent.z {
+                p.y
+            } else {
+                p.z
+            }
+        };
+        indices.sort_by(|&a, &b| {
+            coordinate(bounds[a].center()).total_cmp(&coordinate(bounds[b].center()))
+        });
+        let right = indices.split_off(indices.len() / 2);
+        Bvh::Node(
+            aabb,
+            Box::new(Bvh::build(indices, bounds)),
+            Box::new(Bvh::build(right, bounds)),
+        )
+    }
+
+    fn bounds(&self) -> Aabb {
+        match self {
+            Bvh::Leaf(aabb, _) | Bvh::Node(aabb, _, _) => *aabb,
+        }
+    }
+}
+
+// Coordinates with the ray starting at the origin and running along +z
+struct RayFrame {
+    origin: Vec3D,
+    axes: [Vec3D; 3],
+}
+
+impl RayFrame {
+    fn new(ray: Ray) -> Self {
+        let direction = ray.direction.unit_vector();
+        let (x, y) = orthonormal_basis(direction);
+        RayFrame {
+            origin: ray.origin,
+            axes: [x, y, direction],
+        }
+    }
+
+    fn to_local(&self, p: Vec3D) -> Vec3D {
+        let p = p - self.origin;
+        Vec3D::new(p * self.axes[0], p * self.axes[1], p * self.axes[2])
+    }
+}
+
+// Closest hit so far, z is the distance along the ray
+#[derive(Debug, Clone, Copy)]
+struct CurveHit {
+    segment: usize,
+    z: f64,
+    u: f64,
+    v: f64,
+}
+
+// Hair, fur or grass as strands of curves with varying width. The segments of all strands
+// are kept in a bounding volume hierarchy and intersected in the ray's coordinates by
+// splitting them until they are nearly straight (Nakamaru and Ohno). u runs along every
+// strand from 0 to 1, v across it, and hits report the direction of the strand as tangent
+// for hair materials.
+pub struct Curves {
+    pub shape: CurveShape,
+    pub phong_data: Option<PhongModel>,
+    segments: Vec<Segment>,
+    bvh: Option<Bvh>,
+}
+
+impl Curves {
+    pub fn new(strands: &[Strand], basis: CurveBasis, shape: CurveShape) -> Self {
+        let segments: Vec<Segment> = strands
+            .iter()
+            .flat_map(|strand| strand.segments(basis))
+            .collect();
+        let bounds: Vec<Aabb> = segments
+            .iter()
+            .map(|segment| {
+                let radius = 0.5 * segment.widths.iter().fold(0.0, |a: f64, &b| a.max(b));
+                Aabb::from_points(segment.points)
+                    .unwrap()
+                    .pad(Vec3D::new(radius, radius, radius))
+            })
+            .collect();
+        let bvh = if segments.is_empty() {
+            None
+        } else {
+            Some(Bvh::build((0..segments.len()).collect(), &bounds))
+        };
+        Curves {
+            shape,
+            phong_data: None,
+            segments,
+            bvh,
+        }
+    }
+
+    // Strands in a text file, one per line as "x y z width" for each of its points. Empty
+    // lines and lines starting with # are skipped.
+    pub fn from_strands_file<P: AsRef<Path>>(
+        filename: P,
+        basis: CurveBasis,
+        shape: CurveShape,
+    ) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(filename)?;
+        let mut strands = Vec::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let numbers = line
+                .split_whitespace()
+                .map(|n| n.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()?;
+            if numbers.len() % 4 != 0 {
+                return Err(format!(
+                    "line {}: expected x y z width for every point",
+                    line_number + 1
+                )
+                .into());
+            }
+            strands.push(Strand {
+                points: numbers
+                    .chunks(4)
+                    .map(|point| Vec3D::new(point[0], point[1], point[2]))
+                    .collect(),
+                widths: numbers.chunks(4).map(|point| point[3]).collect(),
+            });
+        }
+        Ok(Curves::new(&strands, basis, shape))
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    fn intersect_node(
+        &self,
+        node: &Bvh,
+        ray: Ray,
+        frame: &RayFrame,
+        z_range: (f64, f64),
+        best: &mut Option<CurveHit>,
+    ) {
+        let scale = ray.direction.norm();
+        let z_max = best.map_or(z_range.1, |hit| hit.z);
+        if !node.bounds().hit(ray, z_range.0 / scale, z_max / scale) {
+            return;
+        }
+        match node {
+            Bvh::Leaf(_, indices) => {
+                for &index in indices {
+                    let segment = &self.segments[index];
+                    let points = segment.points.map(|p| frame.to_local(p));
+                    let depth = subdivision_depth(&points, &segment.widths);
+                    self.intersect_segment(
+                        index,
+                        points,
+                        segment.widths,
+                        segment.u_range,
+                        depth,
+                        z_range,
+                        best,
+                    );
+                }
+            }
+            Bvh::Node(_, left, right) => {
+                self.intersect_node(left, ray, frame, z_range, best);
+                self.intersect_node(right, ray, frame, z_range, best);
+            }
+        }
+    }
+
+    // Tests a part of a segment, given in ray coordinates, for a hit closer than best
+    #[allow(clippy::too_many_arguments)]
+    fn intersect_segment(
+        &self,
+        index: usize,
+        points: [Vec3D; 4],
+        widths: [f64; 4],
+        u_range: (f64, f64),
+        depth: u32,
+        z_range: (f64, f64),
+        best: &mut Option<CurveHit>,
+    ) {
+        let z_max = best.map_or(z_range.1, |hit| hit.z);
+        let radius = 0.5 * widths.iter().fold(0.0, |a: f64, &b| a.max(b));
+        let bounds = Aabb::from_points(points)
+            .unwrap()
+            .pad(Vec3D::new(radius, radius, radius));
+        if bounds.min.x > 0.0
+            || bounds.max.x < 0.0
+            || bounds.min.y > 0.0
+            || bounds.max.y < 0.0
+            || bounds.min.z > z_max
+            || bounds.max.z < z_range.0
+        {
+            return;
+        }
+
+        if depth > 0 {
+            let (first, second) = split_half(points);
+            let (first_widths, second_widths) = split_half(widths);
+            let u_mid = 0.5 * (u_range.0 + u_range.1);
+            self.intersect_segment(
+                index,
+                first,
+                first_widths,
+                (u_range.0, u_mid),
+                depth - 1,
+                z_range,
+                best,
+            );
+            self.intersect_segment(
+                index,
+                second,
+                second_widths,
+                (u_mid, u_range.1),
+                depth - 1,
+                z_range,
+                best,
+            );
+            return;
+        }
+
+        // the ray has to pass between the lines through both ends, perpendicular to the curve,
+        // so neighbouring pieces don't both report the same hit
+        let dot_xy = |a: Vec3D, b: Vec3D| a.x * b.x + a.y * b.y;
+        if dot_xy(-points[0], points[1] - points[0]) < 0.0
+            || dot_xy(-points[3], points[2] - points[3]) < 0.0
+        {
+            return;
+        }
+        // closest point to the ray on the nearly straight piece
+        let chord = points[3] - points[0];
+        let length2 = dot_xy(chord, chord);
+        let w = if length2 > 0.0 {
+            (dot_xy(-points[0], chord) / length2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let point = bezier_at(points, bernstein(w));
+        let width = bezier_at(widths, bernstein(w));
+        let distance2 = point.x * point.x + point.y * point.y;
+        if distance2 > 0.25 * width * width || point.z < z_range.0 || point.z > z_max {
+            return;
+        }
+        // rays leaving a cylinder, like shadow rays of light passing through a hair, don't
+        // hit it again
+        if self.shape == CurveShape::Cylinder && point.norm2() < 0.25 * width * width {
+            return;
+        }
+        // v tells on which side of the curve the ray passes
+        let derivative = bezier_at(points, bernstein_derivative(w));
+        let offset = f64::sqrt(distance2) / width;
+        let v = if derivative.x * -point.y + point.x * derivative.y > 0.0 {
+            0.5 + offset
+        } else {
+            0.5 - offset
+        };
+        let u = u_range.0 + w * (u_range.1 - u_range.0);
+        if let Some(phong_data) = &self.phong_data {
+            if phong_data.is_cut_out(u, v) {
+                return;
+            }
+        }
+        *best = Some(CurveHit {
+            segment: index,
+            z: point.z,
+            u,
+            v,
+        });
+    }
+}
+
+// Number of times a segment has to be halved before it is close to straight, relative to
+// its width (Nakamaru and Ohno)
+fn subdivision_depth(points: &[Vec3D; 4], widths: &[f64; 4]) -> u32 {
+    let mut flatness: f64 = 0.0;
+    for k in 0..2 {
+        let second_difference = points[k] - 2.0 * points[k + 1] + points[k + 2];
+        flatness = flatness
+            .max(second_difference.x.abs())
+            .max(second_difference.y.abs())
+            .max(second_difference.z.abs());
+    }
+    let epsilon = 0.05 * widths.iter().fold(0.0, |a: f64, &b| a.max(b));
+    if flatness <= 0.0 || epsilon <= 0.0 {
+        return 0;
+    }
+    let depth = f64::log2(std::f64::consts::SQRT_2 * 6.0 * flatness / (8.0 * epsilon)) / 2.0;
+    depth.ceil().clamp(0.0, 10.0) as u32
+}
+
+impl Hittable for Curves {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let bvh = self.bvh.as_ref()?;
+        let frame = RayFrame::new(ray);
+        let scale = ray.direction.norm();
+        let mut best = None;
+        self.intersect_node(bvh, ray, &frame, (t_min * scale, t_max * scale), &mut best);
+        let hit = best?;
+
+        let segment = &self.segments[hit.segment];
+        let local = (hit.u - segment.u_range.0) / (segment.u_range.1 - segment.u_range.0);
+        let center = bezier_at(segment.points, bernstein(local));
+        let mut tangent = bezier_at(segment.points, bernstein_derivative(local));
+        if tangent.almost_zero() {
+            tangent = segment.points[3] - segment.points[0];
+        }
+        let tangent = tangent.unit_vector();
+        let width = bezier_at(segment.widths, bernstein(local));
+
+        let (t, outward) = match self.shape {
+            CurveShape::Ribbon => (hit.z / scale, (-ray.direction / scale).perp(tangent)),
+            CurveShape::Cylinder => {
+                // the cylinder around the tangent at the closest point of the curve
+                let m = (ray.origin - center).perp(tangent);
+                let d = ray.direction.perp(tangent);
+                let a = d * d;
+                let b = 2.0 * (m * d);
+                let c = m * m - 0.25 * width * width;
+                let root = f64::sqrt((b * b - 4.0 * a * c).max(0.0));
+                let t = (-b - root) / (2.0 * a);
+                // grazing rays can just miss the idealized cylinder
+                let t = if t_min < t && t < t_max {
+                    t
+                } else {
+                    hit.z / scale
+                };
+                (t, m + t * d)
+            }
+        };
+        // rays along the tangent don't tell which way the surface faces
+        let outward = if outward.almost_zero() {
+            orthonormal_basis(tangent).0
+        } else {
+            outward.unit_vector()
+        };
+        let front_face = ray.direction * outward <= 0.0;
+        Some(IntersectionData {
+            ray,
+            t,
+            normal: if front_face { outward } else { -outward },
+            front_face,
+            phong_data: self.phong_data.as_ref(),
+            u: hit.u,
+            v: hit.v,
+            tangent: Some(tangent),
+        })
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.as_ref().map(Bvh::bounds)
+    }
+}
@@ -0,0 +1,107 @@
+use crate::materials::PhongModel;
+use crate::math::polynomial::solve_quadratic;
+use crate::math::sampling::orthonormal_basis;
+use crate::math::vector::Vec3D;
+use crate::objects::{
+    aabb::{disk_bounds, Aabb},
+    disk::polar_angle,
+    hittables::*,
+    ray::Ray,
+};
+
+// Closed cylinder from base along the unit axis. On the side u runs around the axis and v
+// along it, the caps are mapped like disks.
+#[derive(Debug, Clone)]
+pub struct Cylinder {
+    pub base: Vec3D,
+    pub axis: Vec3D,
+    pub radius: f64,
+    pub height: f64,
+    pub phong_data: Option<PhongModel>,
+}
+
+impl Cylinder {
+    pub fn between(start: Vec3D, end: Vec3D, radius: f64) -> Self {
+        Cylinder {
+            base: start,
+            axis: (end - start).unit_vector(),
+            radius,
+            height: (end - start).norm(),
+            phong_data: None,
+        }
+    }
+}
+
+impl Cylinder {
+    // Crossings of the ray with the surface, in any order and at any t
+    fn candidates(&self, ray: Ray) -> Vec<HitCandidate> {
+        let origin = ray.origin - self.base;
+        let (tangent, bitangent) = orthonormal_basis(self.axis);
+        let (o_y, d_y) = (origin * self.axis, ray.direction * self.axis);
+        let o_perp = origin - o_y * self.axis;
+        let d_perp = ray.direction - d_y * self.axis;
+        let mut candidates = Vec::with_capacity(4);
+
+        // side: |o_perp + t * d_perp|^2 = r^2
+        for t in solve_quadratic(
+            d_perp.norm2(),
+            2.0 * (o_perp * d_perp),
+            o_perp.norm2() - self.radius * self.radius,
+        ) {
+            let y = o_y + t * d_y;
+            if (0.0..=self.height).contains(&y) {
+                let radial = (o_perp + t * d_perp) / self.radius;
+                candidates.push(HitCandidate {
+                    t,
+                    normal: radial,
+                    u: polar_angle(radial * tangent, radial * bitangent),
+                    v: y / self.height,
+                });
+            }
+        }
+
+        // caps
+        if d_y != 0.0 {
+            for (y, sign) in [(0.0, -1.0), (self.height, 1.0)] {
+                let t = (y - o_y) / d_y;
+                let radial = o_perp + t * d_perp;
+                if radial.norm2() <= self.radius * self.radius {
+                    candidates.push(HitCandidate {
+                        t,
+                        normal: sign * self.axis,
+                        u: polar_angle(radial * tangent, radial * bitangent),
+                        v: radial.norm() / self.radius,
+                    });
+                }
+            }
+        }
+        candidates
+    }
+}
+
+impl Hittable for Cylinder {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_hit(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_crossing(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let top = self.base + self.height * self.axis;
+        Some(
+            disk_bounds(self.base, self.axis, self.radius).union(disk_bounds(
+                top,
+                self.axis,
+                self.radius,
+            )),
+        )
+    }
+}
@@ -0,0 +1,53 @@
+use crate::materials::PhongModel;
+use crate::math::sampling::orthonormal_basis;
+use crate::math::vector::Vec3D;
+use crate::objects::{
+    aabb::{disk_bounds, Aabb},
+    hittables::*,
+    ray::Ray,
+};
+
+// Flat disk, u runs around the center and v outwards from it
+#[derive(Debug, Clone)]
+pub struct Disk {
+    pub center: Vec3D,
+    pub normal: Vec3D, // unit length
+    pub radius: f64,
+    pub phong_data: Option<PhongModel>,
+}
+
+impl Hittable for Disk {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let denominator = ray.direction * self.normal;
+        if denominator.abs() <= f64::EPSILON {
+            return None;
+        }
+        let t = (self.center - ray.origin) * self.normal / denominator;
+        let offset = ray.at(t) - self.center;
+        if offset.norm2() > self.radius * self.radius {
+            return None;
+        }
+        let (tangent, bitangent) = orthonormal_basis(self.normal);
+        let mut candidates = [HitCandidate {
+            t,
+            normal: self.normal,
+            u: polar_angle(offset * tangent, offset * bitangent),
+            v: offset.norm() / self.radius,
+        }];
+        closest_hit(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(disk_bounds(self.center, self.normal, self.radius))
+    }
+}
+
+// Angle of (x, y) mapped from [-pi, pi] to [0, 1]
+pub fn polar_angle(x: f64, y: f64) -> f64 {
+    use std::f64::consts::PI;
+    f64::atan2(y, x) / (2.0 * PI) + 0.5
+}
@@ -0,0 +1,182 @@
+use crate::materials::Texture;
+use crate::math::vector::Vec3D;
+use crate::objects::{mesh::Mesh, triangle::Triangle};
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    position: Vec3D,
+    normal: Vec3D,
+    uv: Option<(f64, f64)>, // as stored in Triangle, v pointing up
+}
+
+impl Vertex {
+    fn midpoint(a: Vertex, b: Vertex) -> Vertex {
+        Vertex {
+            position: 0.5 * (a.position + b.position),
+            normal: (a.normal + b.normal).unit_vector(),
+            uv: a
+                .uv
+                .zip(b.uv)
+                .map(|(a, b)| (0.5 * (a.0 + b.0), 0.5 * (a.1 + b.1))),
+        }
+    }
+}
+
+// Every level of splitting can quadruple the triangle count, so a triangle turns into at most
+// 4^10 ones
+const MAX_SPLIT_DEPTH: u32 = 10;
+
+// Recursively splits the edges of a triangle at their midpoints where split tells it to.
+// Whether an edge is split only depends on the edge itself, so two triangles sharing an edge
+// split it the same way and no cracks open between them.
+fn split_triangle<F: Fn(Vertex, Vertex) -> bool>(
+    [a, b, c]: [Vertex; 3],
+    split: &F,
+    depth: u32,
+    triangles: &mut Vec<[Vertex; 3]>,
+) {
+    let children: Vec<[Vertex; 3]> = match (split(a, b), split(b, c), split(c, a)) {
+        _ if depth == 0 => vec![],
+        (false, false, false) => vec![],
+        (true, true, true) => {
+            let (ab, bc, ca) = (
+                Vertex::midpoint(a, b),
+                Vertex::midpoint(b, c),
+                Vertex::midpoint(c, a),
+            );
+            vec![[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+        }
+        (true, false, false) => {
+            let ab = Vertex::midpoint(a, b);
+            vec![[a, ab, c], [ab, b, c]]
+        }
+        (false, true, false) => {
+            let bc = Vertex::midpoint(b, c);
+            vec![[a, b, bc], [a, bc, c]]
+        }
+        (false, false, true) => {
+            let ca = Vertex::midpoint(c, a);
+            vec![[a, b, ca], [ca, b, c]]
+        }
+        (true, true, false) => {
+            let (ab, bc) = (Vertex::midpoint(a, b), Vertex::midpoint(b, c));
+            vec![[ab, b, bc], [a, ab, bc], [a, bc, c]]
+        }
+        (false, true, true) => {
+            let (bc, ca) = (Vertex::midpoint(b, c), Vertex::midpoint(c, a));
+            vec![[ca, bc, c], [a, b, bc], [a, bc, ca]]
+        }
+        (true, false, true) => {
+            let (ab, ca) = (Vertex::midpoint(a, b), Vertex::midpoint(c, a));
+            vec![[a, ab, ca], [ab, b, c], [ab, c, ca]]
+        }
+    };
+    if children.is_empty() {
+        triangles.push([a, b, c]);
+    }
+    for child in children {
+        split_triangle(child, split, depth - 1, triangles);
+    }
+}
+
+// Moves every vertex of mesh along its normal by scale times the brightness of height_map at
+// its texture coordinates. The mesh is tessellated where the displaced surface bends: edges
+// are split while the height map, sampled twice per texel along them, strays more than
+// tolerance from the line between the heights at their ends, down to the size of a texel.
+// Edges longer than max_edge_length, which has to be positive, are always split. Each input
+// triangle is split at most MAX_SPLIT_DEPTH levels deep.
+// Normals are recomputed from the displaced surface. Triangles without texture coordinates
+// aren't displaced, and vertices shared by triangles with different normals (hard edges)
+// can move apart.
+pub fn displace(
+    mesh: &Mesh,
+    height_map: &Texture,
+    scale: f64,
+    tolerance: f64,
+    max_edge_length: f64,
+) -> Mesh {
+    assert!(
+        max_edge_length > 0.0,
+        "max_edge_length must be positive, got {max_edge_length}"
+    );
+    // textures are looked up with v pointing down, see Triangle::interpolate_uv
+    let height = |(u, v): (f64, f64)| scale * height_map.get_height_at(u, 1.0 - v);
+    let split = |a: Vertex, b: Vertex| {
+        if (a.position - b.position).norm() > max_edge_length {
+            return true;
+        }
+        let (Some(uv_a), Some(uv_b)) = (a.uv, b.uv) else {
+            return false;
+        };
+        let texels = f64::max(
+            (uv_a.0 - uv_b.0).abs() * height_map.width as f64,
+            (uv_a.1 - uv_b.1).abs() * height_map.height as f64,
+        );
+        if texels <= 1.0 {
+            return false;
+        }
+        let steps = 2 * texels.ceil().min(256.0) as usize;
+        (1..steps).any(|i| {
+            let s = i as f64 / steps as f64;
+            let uv = (
+                uv_a.0 + s * (uv_b.0 - uv_a.0),
+                uv_a.1 + s * (uv_b.1 - uv_a.1),
+            );
+            let interpolated = height(uv_a) + s * (height(uv_b) - height(uv_a));
+            (height(uv) - interpolated).abs() > tolerance
+        })
+    };
+    let mut tessellated = Vec::with_capacity(mesh.triangle_count());
+    for triangle in mesh.triangles() {
+        let normals = triangle.normal.unwrap_or([triangle.get_plane_normal(); 3]);
+        let positions = [triangle.vert_a, triangle.vert_b, triangle.vert_c];
+        let vertices = [0, 1, 2].map(|i| Vertex {
+            position: positions[i],
+            normal: normals[i].unit_vector(),
+            uv: triangle.uv.map(|uv| uv[i]),
+        });
+        split_triangle(vertices, &split, MAX_SPLIT_DEPTH, &mut tessellated);
+    }
+
+    let offset = |vertex: Vertex| match vertex.uv {
+        Some(uv) => vertex.position + height(uv) * vertex.normal,
+        None => vertex.position,
+    };
+    let displaced: Vec<[Vec3D; 3]> = tessellated
+        .iter()
+        .map(|vertices| vertices.map(offset))
+        .collect();
+
+    // smooth normals, summing the area weighted normals of all triangles at a position
+    let key = |p: Vec3D| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+    let mut normal_sums: HashMap<[u64; 3], Vec3D> = HashMap::new();
+    for [a, b, c] in &displaced {
+        let area_normal = (*b - *a).cross(*c - *a);
+        for p in [a, b, c] {
+            *normal_sums.entry(key(*p)).or_default() += area_normal;
+        }
+    }
+
+    let triangles = tessellated
+        .iter()
+        .zip(&displaced)
+        .map(|(vertices, positions)| Triangle {
+            vert_a: positions[0],
+            vert_b: positions[1],
+            vert_c: positions[2],
+            normal: Some(positions.map(|p| normal_sums[&key(p)].unit_vector())),
+            uv: match vertices.map(|vertex| vertex.uv) {
+                [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+                _ => None,
+            },
+        })
+        .collect();
+    let mut result = Mesh::new(triangles);
+    if mesh.positions.is_single_precision() {
+        result.set_single_precision();
+    }
+    result.phong_data = mesh.phong_data.clone();
+    result
+}
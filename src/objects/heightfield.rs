@@ -0,0 +1,264 @@
+use crate::materials::PhongModel;
+use crate::math::vector::Vec3D;
+use crate::objects::{aabb::Aabb, hittables::*, ray::Ray, triangle::Triangle};
+
+use image::io::Reader as ImageReader;
+use std::error::Error;
+use std::path::Path;
+
+// Terrain given by heights on a regular grid over the xz-plane, starting at origin and
+// spanning size. Every grid cell is split into two triangles with smoothly interpolated
+// normals. u runs along x and v along z over the whole terrain.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    pub origin: Vec3D, // heights are measured from origin.y
+    pub size: (f64, f64),
+    resolution: (usize, usize),
+    heights: Vec<f64>, // z-major: heights[z * nx + x]
+    min_height: f64,
+    max_height: f64,
+    pub phong_data: Option<PhongModel>,
+}
+
+impl Heightfield {
+    pub fn new(
+        origin: Vec3D,
+        size: (f64, f64),
+        resolution: (usize, usize),
+        heights: Vec<f64>,
+    ) -> Self {
+        let (nx, nz) = resolution;
+        assert!(nx >= 2 && nz >= 2);
+        assert_eq!(nx * nz, heights.len());
+        let min_height = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max_height = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        Heightfield {
+            origin,
+            size,
+            resolution,
+            heights,
+            min_height,
+            max_height,
+            phong_data: None,
+        }
+    }
+
+    // Grayscale image (8 or 16 bit) with white at max_height, the image's top row at the
+    // far end (max z) of the terrain
+    pub fn from_image<P: AsRef<Path>>(
+        filename: P,
+        origin: Vec3D,
+        size: (f64, f64),
+        max_height: f64,
+    ) -> Result<Self, Box<dyn Error>> {
+        let image = ImageReader::open(filename)?.decode()?.to_luma16();
+        let (w, h) = (image.width() as usize, image.height() as usize);
+        if w < 2 || h < 2 {
+            return Err("heightfield images need at least 2x2 pixels".into());
+        }
+        let pixels = image.into_vec();
+        let mut heights = Vec::with_capacity(w * h);
+        for z in 0..h {
+            for x in 0..w {
+                heights.push(max_height * pixels[(h - 1 - z) * w + x] as f64 / 65535.0);
+            }
+        }
+        Ok(Heightfield::new(origin, size, (w, h), heights))
+    }
+
+    fn cell_size(&self) -> (f64, f64) {
+        let (nx, nz) = self.resolution;
+        (self.size.0 / (nx - 1) as f64, self.size.1 / (nz - 1) as f64)
+    }
+
+    fn height(&self, x: usize, z: usize) -> f64 {
+        self.heights[z * self.resolution.0 + x]
+    }
+
+    fn vertex(&self, x: usize, z: usize) -> Vec3D {
+        let (dx, dz) = self.cell_size();
+        self.origin + Vec3D::new(x as f64 * dx, self.height(x, z), z as f64 * dz)
+    }
+
+    // Smooth vertex normal from central differences of the neighbouring heights
+    fn vertex_normal(&self, x: usize, z: usize) -> Vec3D {
+        let (nx, nz) = self.resolution;
+        let (dx, dz) = self.cell_size();
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(nx - 1));
+        let (z0, z1) = (z.saturating_sub(1), (z + 1).min(nz - 1));
+        let slope_x = (self.height(x1, z) - self.height(x0, z)) / ((x1 - x0) as f64 * dx);
+        let slope_z = (self.height(x, z1) - self.height(x, z0)) / ((z1 - z0) as f64 * dz);
+        Vec3D::new(-slope_x, 1.0, -slope_z).unit_vector()
+    }
+
+    // Height of the terrain surface above (x, z) in world space, None outside of the grid
+    pub fn get_height_at(&self, x: f64, z: f64) -> Option<f64> {
+        let (dx, dz) = self.cell_size();
+        let (nx, nz) = self.resolution;
+        let (gx, gz) = ((x - self.origin.x) / dx, (z - self.origin.z) / dz);
+        if gx < 0.0 || gz < 0.0 || gx > (nx - 1) as f64 || gz > (nz - 1) as f64 {
+            return None;
+        }
+        let (cx, cz) = (
+            (gx.floor() as usize).min(nx - 2),
+            (gz.floor() as usize).min(nz - 2),
+        );
+        let (fx, fz) = (gx - cx as f64, gz - cz as f64);
+        // matches the triangle split used in cell_triangles
+        let h = if fx + fz <= 1.0 {
+            self.height(cx, cz)
+                + fx * (self.height(cx + 1, cz) - self.height(cx, cz))
+                + fz * (self.height(cx, cz + 1) - self.height(cx, cz))
+        } else {
+            self.height(cx + 1, cz + 1)
+                + (1.0 - fx) * (self.height(cx, cz + 1) - self.height(cx + 1, cz + 1))
+                + (1.0 - fz) * (self.height(cx + 1, cz) - self.height(cx + 1, cz + 1))
+        };
+        Some(self.origin.y + h)
+    }
+
+    // The two triangles of the cell with (x, z) as its lower corner
+    fn cell_triangles(&self, x: usize, z: usize) -> [Triangle; 2] {
+        let corners = [(x, z), (x + 1, z), (x, z + 1), (x + 1, z + 1)];
+        let triangle = |[a, b, c]: [usize; 3]| {
+            let (a, b, c) = (corners[a], corners[b], corners[c]);
+            Triangle {
+                vert_a: self.vertex(a.0, a.1),
+                vert_b: self.vertex(b.0, b.1),
+                vert_c: self.vertex(c.0, c.1),
+                normal: Some([
+                    self.vertex_normal(a.0, a.1),
+                    self.vertex_normal(b.0, b.1),
+                    self.vertex_normal(c.0, c.1),
+                ]),
+                uv: None,
+            }
+        };
+        // wound so the plane normals point up
+        [triangle([0, 2, 1]), triangle([3, 1, 2])]
+    }
+
+    fn point_to_uv(&self, point: Vec3D) -> (f64, f64) {
+        (
+            (point.x - self.origin.x) / self.size.0,
+            (point.z - self.origin.z) / self.size.1,
+        )
+    }
+
+    // Closest hit with the triangles of a cell between t_min and t_max and its shading normal
+    fn intersect_cell(
+        &self,
+        ray: Ray,
+        cell: (usize, usize),
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, Vec3D)> {
+        let mut best: Option<(f64, Vec3D)> = None;
+        for triangle in self.cell_triangles(cell.0, cell.1) {
+            let t_limit = best.map_or(t_max, |(t, _)| t);
+            if let Some((t, b0, b1)) = triangle.get_intersection(ray, t_min, t_limit) {
+                if let Some(phong_data) = &self.phong_data {
+                    let (u, v) = self.point_to_uv(ray.at(t));
+                    if phong_data.is_cut_out(u, v) {
+                        continue;
+                    }
+                }
+                best = Some((t, triangle.interpolate_normal(b0, b1)));
+            }
+        }
+        best
+    }
+}
+
+impl Hittable for Heightfield {
+    // Walks the cells under the ray in order with a 2D DDA over the xz grid, only testing the
+    // triangles of cells where the ray's height range overlaps the heights of the cell
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let (t_start, t_end) = self.bounding_box()?.clip(ray, t_min, t_max)?;
+        let (nx, nz) = self.resolution;
+        let (dx, dz) = self.cell_size();
+        let entry = ray.at(t_start) - self.origin;
+        let to_cell = |p: f64, d: f64, n: usize| ((p / d).floor().max(0.0) as usize).min(n - 2);
+        let (mut cx, mut cz) = (to_cell(entry.x, dx, nx), to_cell(entry.z, dz, nz));
+
+        // ray parameter at the next cell boundary along each axis and between boundaries
+        let axis = |direction: f64, origin: f64, cell: usize, d: f64| -> (i64, f64, f64) {
+            if direction > 0.0 {
+                let boundary = (cell + 1) as f64 * d;
+                (1, (boundary - origin) / direction, d / direction)
+            } else if direction < 0.0 {
+                let boundary = cell as f64 * d;
+                (-1, (boundary - origin) / direction, -d / direction)
+            } else {
+                (0, f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let local_origin = ray.origin - self.origin;
+        let (step_x, mut next_x, delta_x) = axis(ray.direction.x, local_origin.x, cx, dx);
+        let (step_z, mut next_z, delta_z) = axis(ray.direction.z, local_origin.z, cz, dz);
+
+        let mut t_cell = t_start;
+        while t_cell <= t_end {
+            let t_exit = next_x.min(next_z).min(t_end);
+            // skip cells the ray passes entirely above or below
+            let (y0, y1) = (ray.at(t_cell).y, ray.at(t_exit).y);
+            let corners = [
+                self.height(cx, cz),
+                self.height(cx + 1, cz),
+                self.height(cx, cz + 1),
+                self.height(cx + 1, cz + 1),
+            ];
+            let cell_min = self.origin.y + corners.iter().cloned().fold(f64::INFINITY, f64::min);
+            let cell_max =
+                self.origin.y + corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            if y0.min(y1) <= cell_max && y0.max(y1) >= cell_min {
+                if let Some((t, normal)) = self.intersect_cell(ray, (cx, cz), t_min, t_max) {
+                    let (u, v) = self.point_to_uv(ray.at(t));
+                    let mut candidates = [HitCandidate {
+                        t,
+                        normal: normal.unit_vector(),
+                        u,
+                        v,
+                    }];
+                    return closest_hit(
+                        ray,
+                        t_min,
+                        t_max,
+                        &mut candidates,
+                        self.phong_data.as_ref(),
+                    );
+                }
+            }
+            // step into the neighbouring cell
+            if next_x < next_z {
+                let next = cx as i64 + step_x;
+                if next < 0 || next > nx as i64 - 2 {
+                    break;
+                }
+                cx = next as usize;
+                t_cell = next_x;
+                next_x += delta_x;
+            } else {
+                let next = cz as i64 + step_z;
+                if next < 0 || next > nz as i64 - 2 {
+                    break;
+                }
+                cz = next as usize;
+                t_cell = next_z;
+                next_z += delta_z;
+            }
+        }
+        None
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.origin + Vec3D::new(0.0, self.min_height, 0.0),
+            self.origin + Vec3D::new(self.size.0, self.max_height, self.size.1),
+        ))
+    }
+}
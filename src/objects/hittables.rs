@@ -1,19 +1,110 @@
 use crate::materials::PhongModel;
 use crate::math::vector::Vec3D;
-use crate::objects::ray::Ray;
+use crate::objects::{aabb::Aabb, ray::Ray};
+
+use rand::rngs::SmallRng;
 
 pub trait Hittable {
-    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData>;
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>>;
+
+    // Like intersect, but also reports hits on parts of the surface cut out by the material.
+    // CSG uses it to track which side of its children a ray is on.
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.intersect(ray, t_min, t_max)
+    }
 
     fn get_phong_data(&self) -> Option<&PhongModel>;
+
+    // Picks a random point on the surface as seen from the point `from`. Used to sample
+    // emissive objects as area lights, objects that can't be sampled return None.
+    fn sample_surface(&self, _rng: &mut SmallRng, _from: Vec3D) -> Option<SurfaceSample> {
+        None
+    }
+
+    // Area density with which sample_surface, called with `from`, returns point
+    fn sample_pdf(&self, _from: Vec3D, _point: Vec3D) -> f64 {
+        0.0
+    }
+
+    // Box enclosing the whole object, None for unbounded objects like planes
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    // Whether point lies inside of the object. Only objects a ray can be inside of without
+    // ever crossing their surface, like the half-space behind a plane, need to implement it,
+    // CSG uses the direction of the first crossing along the ray for the others.
+    fn contains(&self, _point: Vec3D) -> Option<bool> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceSample {
+    pub point: Vec3D,
+    pub normal: Vec3D,
+    pub u: f64,
+    pub v: f64,
+    pub pdf: f64, // with respect to surface area
 }
 
 #[derive(Debug, Clone)]
 pub struct IntersectionData<'a> {
     pub ray: Ray,
     pub t: f64,
-    pub normal: Vec3D,
+    pub normal: Vec3D,    // always faces the incoming ray
+    pub front_face: bool, // false if the ray hit the surface from the inside
     pub phong_data: Option<&'a PhongModel>,
     pub u: f64,
     pub v: f64,
+    pub tangent: Option<Vec3D>, // direction of fibers for anisotropic materials like hair
+}
+
+// A point where a ray crosses the surface of an object, normal pointing out of the object
+#[derive(Debug, Clone, Copy)]
+pub struct HitCandidate {
+    pub t: f64,
+    pub normal: Vec3D,
+    pub u: f64,
+    pub v: f64,
+}
+
+// Picks the closest candidate between t_min and t_max that isn't cut out by the material
+pub fn closest_hit<'a>(
+    ray: Ray,
+    t_min: f64,
+    t_max: f64,
+    candidates: &mut [HitCandidate],
+    phong_data: Option<&'a PhongModel>,
+) -> Option<IntersectionData<'a>> {
+    candidates.sort_by(|a, b| a.t.total_cmp(&b.t));
+    candidates
+        .iter()
+        .filter(|hit| t_min < hit.t && hit.t < t_max)
+        .find(|hit| !phong_data.is_some_and(|phong_data| phong_data.is_cut_out(hit.u, hit.v)))
+        .map(|hit| {
+            let front_face = ray.direction * hit.normal < 0.0;
+            IntersectionData {
+                ray,
+                t: hit.t,
+                normal: if front_face { hit.normal } else { -hit.normal },
+                front_face,
+                phong_data,
+                u: hit.u,
+                v: hit.v,
+                tangent: None,
+            }
+        })
+}
+
+// Picks the closest candidate between t_min and t_max, cut out or not
+pub fn closest_crossing<'a>(
+    ray: Ray,
+    t_min: f64,
+    t_max: f64,
+    candidates: &mut [HitCandidate],
+    phong_data: Option<&'a PhongModel>,
+) -> Option<IntersectionData<'a>> {
+    closest_hit(ray, t_min, t_max, candidates, None)
+        .map(|hit| IntersectionData { phong_data, ..hit })
 }
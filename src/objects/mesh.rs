@@ -1,153 +1,637 @@
 use crate::materials::PhongModel;
+use crate::math::sampling::uniform_triangle;
 use crate::math::vector::Vec3D;
-use crate::objects::{hittables::*, ray::*, triangle::Triangle};
+use crate::objects::{aabb::Aabb, hittables::*, ray::*, triangle::Triangle};
 
+use rand::{rngs::SmallRng, Rng};
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::sync::OnceLock;
 
+// Contents of an OBJ file before triangulation. Faces are lists of corners, each with a
+// position, uv and normal index counted from 0.
+#[derive(Debug, Default)]
+pub(crate) struct ObjFile {
+    pub positions: Vec<Vec3D>,
+    pub normals: Vec<Vec3D>,
+    pub uvs: Vec<(f64, f64)>,
+    pub faces: Vec<Vec<[u32; 3]>>,
+}
+
+impl ObjFile {
+    // index of corners without uv or normal
+    pub const MISSING: u32 = u32::MAX;
+
+    // Errors name the line they occur on. Faces can only use vertices, texture coordinates
+    // and normals defined before them, with indices counting from 1, relative indices aren't
+    // supported.
+    pub fn load<P: AsRef<Path>>(filename: P) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(filename)?;
+        let mut obj = ObjFile::default();
+        for (number, line) in io::BufReader::new(file).lines().enumerate() {
+            let error = |message: String| -> Box<dyn Error> {
+                format!("line {}: {}", number + 1, message).into()
+            };
+            let line = line.map_err(|e| error(e.to_string()))?;
+            let mut values = line.split_whitespace();
+            let keyword = values.next();
+            let numbers = || {
+                values
+                    .clone()
+                    .map(|n| n.parse::<f64>())
+                    .collect::<Result<Vec<f64>, _>>()
+                    .map_err(|e| error(e.to_string()))
+            };
+            match keyword {
+                Some("v") | Some("vn") => {
+                    let coords = numbers()?;
+                    if coords.len() < 3 {
+                        return Err(error("positions and normals need three coordinates".into()));
+                    }
+                    let vector = Vec3D::new(coords[0], coords[1], coords[2]);
+                    if keyword == Some("v") {
+                        obj.positions.push(vector);
+                    } else {
+                        obj.normals.push(vector);
+                    }
+                }
+                Some("vt") => {
+                    let coords = numbers()?;
+                    if coords.len() < 2 {
+                        return Err(error("texture coordinates need two values".into()));
+                    }
+                    obj.uvs.push((coords[0], coords[1]));
+                }
+                Some("f") => {
+                    let counts = [obj.positions.len(), obj.uvs.len(), obj.normals.len()];
+                    let mut face: Vec<[u32; 3]> = Vec::new();
+                    for value in values {
+                        let mut corner = [ObjFile::MISSING; 3];
+                        for (i, index) in value.split('/').take(3).enumerate() {
+                            // only texture coordinates and normals are optional
+                            if i > 0 && index.is_empty() {
+                                continue;
+                            }
+                            corner[i] = index
+                                .parse::<u32>()
+                                .ok()
+                                .and_then(|index| index.checked_sub(1))
+                                .filter(|&index| (index as usize) < counts[i])
+                                .ok_or_else(|| error(format!("invalid index {index:?}")))?;
+                        }
+                        face.push(corner);
+                    }
+                    if face.len() < 3 {
+                        return Err(error("faces need at least three vertices".into()));
+                    }
+                    obj.faces.push(face);
+                }
+                _ => (),
+            }
+        }
+        Ok(obj)
+    }
+}
+
+// How the normals of the triangles around a vertex are weighted when averaging them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalWeighting {
+    Area,  // by triangle area, big triangles dominate
+    Angle, // by the angle at the vertex, independent of how the surface is triangulated
+}
+
+// Vertex attributes with N components each, single precision halves the memory they take
+#[derive(Debug, Clone)]
+pub enum VertexBuffer<const N: usize> {
+    F64(Vec<[f64; N]>),
+    F32(Vec<[f32; N]>),
+}
+
+impl<const N: usize> Default for VertexBuffer<N> {
+    fn default() -> Self {
+        VertexBuffer::F64(Vec::new())
+    }
+}
+
+impl<const N: usize> VertexBuffer<N> {
+    pub fn new(single_precision: bool) -> Self {
+        if single_precision {
+            VertexBuffer::F32(Vec::new())
+        } else {
+            VertexBuffer::F64(Vec::new())
+        }
+    }
+
+    pub fn push(&mut self, value: [f64; N]) {
+        match self {
+            VertexBuffer::F64(values) => values.push(value),
+            VertexBuffer::F32(values) => values.push(value.map(|x| x as f32)),
+        }
+    }
+
+    pub fn get(&self, index: u32) -> [f64; N] {
+        match self {
+            VertexBuffer::F64(values) => values[index as usize],
+            VertexBuffer::F32(values) => values[index as usize].map(f64::from),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            VertexBuffer::F64(values) => values.len(),
+            VertexBuffer::F32(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_single_precision(&self) -> bool {
+        matches!(self, VertexBuffer::F32(_))
+    }
+
+    pub fn to_single_precision(&self) -> Self {
+        match self {
+            VertexBuffer::F64(values) => {
+                VertexBuffer::F32(values.iter().map(|value| value.map(|x| x as f32)).collect())
+            }
+            VertexBuffer::F32(_) => self.clone(),
+        }
+    }
+}
+
+fn to_vec3d([x, y, z]: [f64; 3]) -> Vec3D {
+    Vec3D::new(x, y, z)
+}
+
+// Triangle mesh with shared vertices. Every vertex has a position and, if the buffers aren't
+// empty, a normal and texture coordinates. Triangles are triples of vertex indices.
 pub struct Mesh {
-    pub triangles: Vec<Triangle>,
+    pub positions: VertexBuffer<3>,
+    pub normals: VertexBuffer<3>, // empty for flat shading
+    pub uvs: VertexBuffer<2>,
+    pub indices: Vec<[u32; 3]>,
     pub phong_data: Option<PhongModel>,
+    area_cdf: OnceLock<Vec<f64>>, // cumulative triangle areas, built when the mesh is first sampled
+}
+
+// Collects triangles into buffers, merging vertices with identical attributes
+struct MeshBuilder {
+    with_normals: bool,
+    with_uvs: bool,
+    vertices: HashMap<[u64; 8], u32>,
+    positions: Vec<[f64; 3]>,
+    normals: Vec<[f64; 3]>,
+    uvs: Vec<[f64; 2]>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl MeshBuilder {
+    fn new(with_normals: bool, with_uvs: bool) -> Self {
+        MeshBuilder {
+            with_normals,
+            with_uvs,
+            vertices: HashMap::new(),
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    // Triangles without normals get their plane normal, triangles without texture coordinates
+    // ones that reproduce the barycentric coordinates, if other triangles have them
+    fn add_triangle(&mut self, triangle: &Triangle) {
+        let positions = [triangle.vert_a, triangle.vert_b, triangle.vert_c];
+        let normals = triangle.normal.unwrap_or([triangle.get_plane_normal(); 3]);
+        let uvs = triangle.uv.unwrap_or([(1.0, 1.0), (0.0, 0.0), (0.0, 1.0)]);
+        let face = [0, 1, 2].map(|i| {
+            let position = [positions[i].x, positions[i].y, positions[i].z];
+            let normal = if self.with_normals {
+                [normals[i].x, normals[i].y, normals[i].z]
+            } else {
+                [0.0; 3]
+            };
+            let uv = if self.with_uvs {
+                [uvs[i].0, uvs[i].1]
+            } else {
+                [0.0; 2]
+            };
+            let key = [
+                position[0],
+                position[1],
+                position[2],
+                normal[0],
+                normal[1],
+                normal[2],
+                uv[0],
+                uv[1],
+            ]
+            .map(f64::to_bits);
+            let next = self.positions.len() as u32;
+            let index = *self.vertices.entry(key).or_insert(next);
+            if index == next {
+                self.positions.push(position);
+                if self.with_normals {
+                    self.normals.push(normal);
+                }
+                if self.with_uvs {
+                    self.uvs.push(uv);
+                }
+            }
+            index
+        });
+        self.indices.push(face);
+    }
+
+    fn build(self) -> Mesh {
+        Mesh {
+            positions: VertexBuffer::F64(self.positions),
+            normals: VertexBuffer::F64(self.normals),
+            uvs: VertexBuffer::F64(self.uvs),
+            indices: self.indices,
+            phong_data: None,
+            area_cdf: OnceLock::new(),
+        }
+    }
 }
 
 impl Mesh {
+    // Mesh of separate triangles, vertices with the same attributes are shared
+    pub fn new(triangles: Vec<Triangle>) -> Self {
+        let mut builder = MeshBuilder::new(
+            triangles.iter().any(|triangle| triangle.normal.is_some()),
+            triangles.iter().any(|triangle| triangle.uv.is_some()),
+        );
+        for triangle in &triangles {
+            builder.add_triangle(triangle);
+        }
+        builder.build()
+    }
+
+    // Mesh from vertex buffers, normals and uvs are either empty or given for every vertex
+    pub fn indexed(
+        positions: Vec<Vec3D>,
+        normals: Vec<Vec3D>,
+        uvs: Vec<(f64, f64)>,
+        indices: Vec<[u32; 3]>,
+    ) -> Self {
+        assert!(normals.is_empty() || normals.len() == positions.len());
+        assert!(uvs.is_empty() || uvs.len() == positions.len());
+        assert!(indices
+            .iter()
+            .flatten()
+            .all(|&i| (i as usize) < positions.len()));
+        let to_array = |v: &Vec3D| [v.x, v.y, v.z];
+        Mesh {
+            positions: VertexBuffer::F64(positions.iter().map(to_array).collect()),
+            normals: VertexBuffer::F64(normals.iter().map(to_array).collect()),
+            uvs: VertexBuffer::F64(uvs.iter().map(|&(u, v)| [u, v]).collect()),
+            indices,
+            phong_data: None,
+            area_cdf: OnceLock::new(),
+        }
+    }
+
     pub fn from_file<P: AsRef<Path>>(filename: P) -> Result<Self, Box<dyn Error>> {
-        let file = File::open(filename)?;
-        let lines = io::BufReader::new(file).lines();
-        let mut vbo: Vec<Vec3D> = Vec::new();
-        let mut triangles: Vec<Triangle> = Vec::new();
-        let mut normals: Vec<Vec3D> = Vec::new();
-        let mut uvs: Vec<(f64, f64)> = Vec::new();
-        for line in lines {
-            if let Ok(data) = line {
-                if data.len() > 0 {
-                    match &data[..2] {
-                        "v " => {
-                            let vertex = Vec3D::from_vec(
-                                data[2..]
-                                    .split(" ")
-                                    .filter(|x| !x.is_empty())
-                                    .map(|n| n.parse::<f64>().unwrap())
-                                    .collect(),
-                            );
-                            vbo.push(vertex);
-                        }
-                        "f " => {
-                            let mut vertices: Vec<usize> = Vec::new();
-                            let mut vts: Vec<usize> = Vec::new();
-                            let mut vns: Vec<usize> = Vec::new();
-                            let values: Vec<&str> =
-                                data[2..].split(" ").filter(|x| !x.is_empty()).collect();
-                            let n_vertices = values.len();
-                            for value in values.into_iter() {
-                                if let Some((v, rest)) = value.split_once("/") {
-                                    vertices.push(v.parse::<usize>().unwrap());
-                                    if rest.len() > 0 {
-                                        if let Some((vt, rest2)) = rest.split_once("/") {
-                                            if vt.len() > 0 {
-                                                vts.push(vt.parse::<usize>().unwrap());
-                                            }
-                                            if rest2.len() > 0 {
-                                                vns.push(rest2.parse::<usize>().unwrap());
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    vertices.push(value.parse::<usize>().unwrap());
-                                }
-                            }
-                            assert!(n_vertices >= 3);
-                            (0..(n_vertices - 2)).for_each(|i| {
-                                let vertex_normals = if vns.len() > 0 {
-                                    Some([
-                                        normals[vns[0] - 1],
-                                        normals[vns[i + 1] - 1],
-                                        normals[vns[i + 2] - 1],
-                                    ])
-                                } else {
-                                    None
-                                };
-                                let vertex_uvs = if vts.len() > 0 {
-                                    Some([
-                                        uvs[vts[0] - 1],
-                                        uvs[vts[i + 1] - 1],
-                                        uvs[vts[i + 2] - 1],
-                                    ])
-                                } else {
-                                    None
-                                };
-                                let triangle = Triangle {
-                                    vert_a: vbo[vertices[0] - 1],
-                                    vert_b: vbo[vertices[i + 1] - 1],
-                                    vert_c: vbo[vertices[i + 2] - 1],
-                                    normal: vertex_normals,
-                                    uv: vertex_uvs,
-                                };
-                                triangles.push(triangle);
-                            })
-                        }
-                        "vn" => {
-                            let normal = Vec3D::from_vec(
-                                data[2..]
-                                    .split(" ")
-                                    .filter(|x| !x.is_empty())
-                                    .map(|n| n.parse::<f64>().unwrap())
-                                    .collect(),
-                            );
-                            normals.push(normal);
-                        }
-                        "vt" => {
-                            let vts: Vec<f64> = data[2..]
-                                .split(" ")
-                                .filter(|x| !x.is_empty())
-                                .map(|n| n.parse::<f64>().unwrap())
-                                .collect();
-                            let uv = (vts[0], vts[1]);
-                            uvs.push(uv);
-                        }
-                        _ => (),
+        Mesh::load_obj(filename, false)
+    }
+
+    // Like from_file followed by set_single_precision, but the buffers are filled in single
+    // precision right away, so big files never need the memory for both
+    pub fn from_file_single_precision<P: AsRef<Path>>(filename: P) -> Result<Self, Box<dyn Error>> {
+        Mesh::load_obj(filename, true)
+    }
+
+    fn load_obj<P: AsRef<Path>>(
+        filename: P,
+        single_precision: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let ObjFile {
+            positions: vbo,
+            mut normals,
+            mut uvs,
+            faces,
+        } = ObjFile::load(filename)?;
+
+        // triangles without normals or uvs in files where others have them are filled in
+        // like in MeshBuilder::add_triangle
+        let corners = || faces.iter().flatten();
+        let with_uvs = corners().any(|corner| corner[1] != ObjFile::MISSING);
+        let with_normals = corners().any(|corner| corner[2] != ObjFile::MISSING);
+        let barycentric_uvs = uvs.len() as u32;
+        if with_uvs {
+            uvs.extend([(1.0, 1.0), (0.0, 0.0), (0.0, 1.0)]);
+        }
+        let mut vertices: HashMap<[u32; 3], u32> = HashMap::new();
+        let mut mesh_positions = VertexBuffer::new(single_precision);
+        let mut mesh_normals = VertexBuffer::new(single_precision);
+        let mut mesh_uvs = VertexBuffer::new(single_precision);
+        let mut indices = Vec::with_capacity(faces.len());
+        for face in &faces {
+            for i in 1..face.len() - 1 {
+                let mut triangle = [face[0], face[i], face[i + 1]];
+                if with_normals && triangle.iter().any(|corner| corner[2] == ObjFile::MISSING) {
+                    let [a, b, c] = triangle.map(|corner| vbo[corner[0] as usize]);
+                    normals.push((b - a).cross(c - a).unit_vector());
+                    triangle
+                        .iter_mut()
+                        .for_each(|corner| corner[2] = normals.len() as u32 - 1);
+                }
+                if with_uvs && triangle.iter().any(|corner| corner[1] == ObjFile::MISSING) {
+                    for (i, corner) in triangle.iter_mut().enumerate() {
+                        corner[1] = barycentric_uvs + i as u32;
                     }
                 }
+                indices.push(triangle.map(|[v, vt, vn]| {
+                    let key = [
+                        v,
+                        if with_uvs { vt } else { 0 },
+                        if with_normals { vn } else { 0 },
+                    ];
+                    *vertices.entry(key).or_insert_with(|| {
+                        let position = vbo[v as usize];
+                        mesh_positions.push([position.x, position.y, position.z]);
+                        if with_normals {
+                            let normal = normals[vn as usize];
+                            mesh_normals.push([normal.x, normal.y, normal.z]);
+                        }
+                        if with_uvs {
+                            let (u, v) = uvs[vt as usize];
+                            mesh_uvs.push([u, v]);
+                        }
+                        mesh_positions.len() as u32 - 1
+                    })
+                }));
             }
         }
         Ok(Mesh {
-            triangles: triangles,
+            positions: mesh_positions,
+            normals: mesh_normals,
+            uvs: mesh_uvs,
+            indices,
             phong_data: None,
+            area_cdf: OnceLock::new(),
+        })
+    }
+
+    fn position(&self, index: u32) -> Vec3D {
+        to_vec3d(self.positions.get(index))
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    // The triangle with the given index, with its vertex attributes
+    pub fn triangle(&self, index: usize) -> Triangle {
+        let corners = self.indices[index];
+        Triangle {
+            normal: (!self.normals.is_empty())
+                .then(|| corners.map(|i| to_vec3d(self.normals.get(i)))),
+            uv: (!self.uvs.is_empty()).then(|| {
+                corners.map(|i| {
+                    let [u, v] = self.uvs.get(i);
+                    (u, v)
+                })
+            }),
+            ..self.triangle_corners(index)
+        }
+    }
+
+    // Only the positions of the triangle, enough to test for intersections
+    fn triangle_corners(&self, index: usize) -> Triangle {
+        let [a, b, c] = self.indices[index];
+        Triangle {
+            vert_a: self.position(a),
+            vert_b: self.position(b),
+            vert_c: self.position(c),
+            normal: None,
+            uv: None,
+        }
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = Triangle> + '_ {
+        (0..self.indices.len()).map(|index| self.triangle(index))
+    }
+
+    // Stores the vertex attributes in single precision
+    pub fn set_single_precision(&mut self) {
+        self.positions = self.positions.to_single_precision();
+        self.normals = self.normals.to_single_precision();
+        self.uvs = self.uvs.to_single_precision();
+    }
+
+    // Replaces the buffers by the ones collected by builder, keeping the precision
+    fn rebuild(&mut self, builder: MeshBuilder) {
+        let single_precision = self.positions.is_single_precision();
+        let mesh = builder.build();
+        self.positions = mesh.positions;
+        self.normals = mesh.normals;
+        self.uvs = mesh.uvs;
+        self.indices = mesh.indices;
+        self.area_cdf = OnceLock::new();
+        if single_precision {
+            self.set_single_precision();
+        }
+    }
+
+    fn get_area_cdf(&self) -> &Vec<f64> {
+        self.area_cdf.get_or_init(|| {
+            let mut total = 0.0;
+            (0..self.indices.len())
+                .map(|index| {
+                    total += self.triangle_corners(index).area();
+                    total
+                })
+                .collect()
         })
     }
 
     pub fn set_phong_data(&mut self, phong_data: PhongModel) {
         self.phong_data = Some(phong_data);
     }
-}
 
-impl Hittable for Mesh {
-    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData> {
+    // Moves vertices closer than tolerance to each other onto the same position, so
+    // triangles of a scan or of a file with duplicated vertices become connected. Triangles
+    // collapsing to a line or point are removed. The tolerance has to be positive.
+    pub fn weld_vertices(&mut self, tolerance: f64) {
+        assert!(
+            tolerance > 0.0,
+            "weld tolerance must be positive, got {tolerance}"
+        );
+        let cell =
+            |p: Vec3D| [p.x, p.y, p.z].map(|coordinate| (coordinate / tolerance).floor() as i64);
+        let mut welded: Vec<Vec3D> = Vec::new();
+        let mut grid: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut weld = |p: Vec3D| {
+            let [x, y, z] = cell(p);
+            for neighbour in (-1..=1).flat_map(|dx| {
+                (-1..=1).flat_map(move |dy| {
+                    (-1..=1).map(move |dz| {
+                        // coordinates far beyond the tolerance saturate at the ends of i64
+                        [
+                            x.saturating_add(dx),
+                            y.saturating_add(dy),
+                            z.saturating_add(dz),
+                        ]
+                    })
+                })
+            }) {
+                if let Some(indices) = grid.get(&neighbour) {
+                    if let Some(&index) = indices
+                        .iter()
+                        .find(|&&index| (welded[index] - p).norm() <= tolerance)
+                    {
+                        return welded[index];
+                    }
+                }
+            }
+            grid.entry([x, y, z]).or_default().push(welded.len());
+            welded.push(p);
+            p
+        };
+        let positions: Vec<Vec3D> = (0..self.vertex_count() as u32)
+            .map(|index| weld(self.position(index)))
+            .collect();
+        let mut builder = MeshBuilder::new(!self.normals.is_empty(), !self.uvs.is_empty());
+        for (index, [a, b, c]) in self.indices.iter().enumerate() {
+            let triangle = Triangle {
+                vert_a: positions[*a as usize],
+                vert_b: positions[*b as usize],
+                vert_c: positions[*c as usize],
+                ..self.triangle(index)
+            };
+            if triangle.area() > 0.0 {
+                builder.add_triangle(&triangle);
+            }
+        }
+        self.rebuild(builder);
+    }
+
+    // Replaces the vertex normals by averages of the normals of the triangles sharing each
+    // vertex position. Triangles meeting at an angle above crease_angle (in radians) don't
+    // share their normals, so hard edges stay sharp.
+    pub fn generate_normals(&mut self, weighting: NormalWeighting, crease_angle: f64) {
+        // vertices at the same position, like on both sides of a texture seam, are one
+        let mut positions: HashMap<[u64; 3], usize> = HashMap::new();
+        let group: Vec<usize> = (0..self.vertex_count() as u32)
+            .map(|index| {
+                let key = self.positions.get(index).map(f64::to_bits);
+                let next = positions.len();
+                *positions.entry(key).or_insert(next)
+            })
+            .collect();
+        let mut around_vertex: Vec<Vec<(usize, usize)>> = vec![Vec::new(); positions.len()];
+        for (index, face) in self.indices.iter().enumerate() {
+            for (corner, &vertex) in face.iter().enumerate() {
+                around_vertex[group[vertex as usize]].push((index, corner));
+            }
+        }
+        let face_normals: Vec<Vec3D> = (0..self.triangle_count())
+            .map(|index| self.triangle_corners(index).get_plane_normal())
+            .collect();
+        // weight of every corner of every triangle
+        let weights: Vec<[f64; 3]> = (0..self.triangle_count())
+            .map(|index| self.triangle_corners(index))
+            .map(|triangle| match weighting {
+                NormalWeighting::Area => [triangle.area(); 3],
+                NormalWeighting::Angle => {
+                    let p = [triangle.vert_a, triangle.vert_b, triangle.vert_c];
+                    [0, 1, 2].map(|i| {
+                        let (a, b) = (p[(i + 1) % 3] - p[i], p[(i + 2) % 3] - p[i]);
+                        f64::acos((a.unit_vector() * b.unit_vector()).clamp(-1.0, 1.0))
+                    })
+                }
+            })
+            .collect();
+
+        let cos_crease = f64::cos(crease_angle);
+        let mut builder = MeshBuilder::new(true, !self.uvs.is_empty());
+        for index in 0..self.triangle_count() {
+            let mut triangle = self.triangle(index);
+            let face_normal = face_normals[index];
+            triangle.normal = Some(self.indices[index].map(|vertex| {
+                let mut sum = Vec3D::default();
+                for &(other, corner) in &around_vertex[group[vertex as usize]] {
+                    // degenerate triangles have NaN normals and fail the test
+                    if face_normals[other] * face_normal >= cos_crease {
+                        sum += weights[other][corner] * face_normals[other];
+                    }
+                }
+                if sum.norm2() > 0.0 {
+                    sum.unit_vector()
+                } else {
+                    face_normal
+                }
+            }));
+            builder.add_triangle(&triangle);
+        }
+        self.rebuild(builder);
+    }
+
+    // Skips hits on parts of the surface cut out by the material if cut_outs is set
+    fn first_hit(
+        &self,
+        ray: Ray,
+        t_min: f64,
+        t_max: f64,
+        cut_outs: bool,
+    ) -> Option<IntersectionData<'_>> {
         let mut best = t_max;
-        let mut winner: Option<Triangle> = None;
-        for triangle in &self.triangles {
-            if let Some(t) = triangle.get_intersection(ray, t_min, best) {
+        let mut winner: Option<(usize, f64, f64)> = None;
+        for index in 0..self.indices.len() {
+            if let Some((t, b0, b1)) = self
+                .triangle_corners(index)
+                .get_intersection(ray, t_min, best)
+            {
+                if let Some(phong_data) = self.phong_data.as_ref().filter(|_| cut_outs) {
+                    let (u, v) = self.triangle(index).interpolate_uv(b0, b1);
+                    if phong_data.is_cut_out(u, v) {
+                        continue;
+                    }
+                }
                 best = t;
-                winner = Some(*triangle);
+                winner = Some((index, b0, b1));
             }
         }
-        if let Some(triangle) = winner {
-            let p = ray.at(best);
-            let (u, v) = triangle.point_to_uv(p);
+        if let Some((index, b0, b1)) = winner {
+            let triangle = self.triangle(index);
+            let (u, v) = triangle.interpolate_uv(b0, b1);
+            let front_face = ray.direction * triangle.get_plane_normal() < 0.0;
+            let normal = triangle.interpolate_normal(b0, b1);
             Some(IntersectionData {
-                ray: ray,
+                ray,
                 t: best,
-                normal: triangle.get_normal_at(p),
+                normal: if front_face { normal } else { -normal },
+                front_face,
                 phong_data: self.phong_data.as_ref(),
-                u: u,
-                v: v,
+                u,
+                v,
+                tangent: None,
             })
         } else {
             None
         }
     }
+}
+
+impl Hittable for Mesh {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, true)
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, false)
+    }
 
     /*fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData> {
         for triangle in &self.triangles {
@@ -169,4 +653,40 @@ impl Hittable for Mesh {
     fn get_phong_data(&self) -> Option<&PhongModel> {
         self.phong_data.as_ref()
     }
+
+    // Picks a triangle with probability proportional to its area and a uniform point on it
+    fn sample_surface(&self, rng: &mut SmallRng, _from: Vec3D) -> Option<SurfaceSample> {
+        let cdf = self.get_area_cdf();
+        let total_area = *cdf.last()?;
+        let target = rng.gen::<f64>() * total_area;
+        let idx = cdf
+            .partition_point(|&area| area < target)
+            .min(cdf.len() - 1);
+        let triangle = self.triangle(idx);
+        let (b0, b1) = uniform_triangle(rng);
+        let (u, v) = triangle.interpolate_uv(b0, b1);
+        Some(SurfaceSample {
+            point: triangle.point_at(b0, b1),
+            normal: triangle.get_plane_normal(),
+            u,
+            v,
+            pdf: 1.0 / total_area,
+        })
+    }
+
+    fn sample_pdf(&self, _from: Vec3D, _point: Vec3D) -> f64 {
+        match self.get_area_cdf().last() {
+            Some(total_area) => 1.0 / total_area,
+            None => 0.0,
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points(
+            self.indices
+                .iter()
+                .flatten()
+                .map(|&index| self.position(index)),
+        )
+    }
 }
@@ -0,0 +1,156 @@
+use crate::animation::{Interpolation, Track};
+use crate::materials::PhongModel;
+use crate::math::vector::Vec3D;
+use crate::objects::{aabb::Aabb, hittables::*, ray::Ray};
+
+use std::sync::Arc;
+
+// Rotates v by the angle |rotation| (in radians) around the axis along rotation
+pub fn rotate(v: Vec3D, rotation: Vec3D) -> Vec3D {
+    let angle = rotation.norm();
+    if angle == 0.0 {
+        return v;
+    }
+    let axis = rotation / angle;
+    let (sin, cos) = angle.sin_cos();
+    cos * v + sin * axis.cross(v) + ((1.0 - cos) * (axis * v)) * axis
+}
+
+// Rigid transform of an object at a moment in time. The object is rotated around its
+// origin first, rotation given as axis times angle in radians.
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3D,
+    pub rotation: Vec3D,
+}
+
+#[derive(Debug, Clone)]
+pub enum Motion {
+    Linear {
+        velocity: Vec3D, // distance per unit of time, starting from the origin at time 0
+    },
+    // animation tracks, a track without keys leaves the object where it is
+    Keyframes {
+        translation: Track<Vec3D>,
+        rotation: Track<Vec3D>,
+    },
+}
+
+impl Motion {
+    // Keyframes interpolated linearly and held at both ends
+    pub fn from_keyframes(keyframes: &[Keyframe]) -> Self {
+        let track = |value: fn(&Keyframe) -> Vec3D| {
+            let keys: Vec<(f64, Vec3D)> = keyframes
+                .iter()
+                .map(|keyframe| (keyframe.time, value(keyframe)))
+                .collect();
+            Track::new(Interpolation::Linear, &keys)
+        };
+        Motion::Keyframes {
+            translation: track(|keyframe| keyframe.translation),
+            rotation: track(|keyframe| keyframe.rotation),
+        }
+    }
+
+    // Translation and rotation at time
+    pub fn transform_at(&self, time: f64) -> (Vec3D, Vec3D) {
+        match self {
+            Motion::Linear { velocity } => (time * *velocity, Vec3D::default()),
+            Motion::Keyframes {
+                translation,
+                rotation,
+            } => (
+                translation.value_at(time).unwrap_or_default(),
+                rotation.value_at(time).unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+// An object moving over time. Rays are moved into the object's own coordinates at their
+// time before they are intersected with it.
+pub struct Moving {
+    pub object: Arc<dyn Hittable + Send + Sync>,
+    pub motion: Motion,
+}
+
+impl Moving {
+    // Skips hits on parts of the surface cut out by the material if cut_outs is set
+    fn first_hit(
+        &self,
+        ray: Ray,
+        t_min: f64,
+        t_max: f64,
+        cut_outs: bool,
+    ) -> Option<IntersectionData<'_>> {
+        let (translation, rotation) = self.motion.transform_at(ray.time);
+        let local_ray = Ray {
+            origin: rotate(ray.origin - translation, -rotation),
+            direction: rotate(ray.direction, -rotation),
+            time: ray.time,
+        };
+        let hit = if cut_outs {
+            self.object.intersect(local_ray, t_min, t_max)
+        } else {
+            self.object.intersect_solid(local_ray, t_min, t_max)
+        }?;
+        Some(IntersectionData {
+            ray,
+            normal: rotate(hit.normal, rotation),
+            tangent: hit.tangent.map(|tangent| rotate(tangent, rotation)),
+            ..hit
+        })
+    }
+}
+
+impl Hittable for Moving {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, true)
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, false)
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.object.get_phong_data()
+    }
+
+    // Animated objects stay within reach of their origin, which stays within the hull of the
+    // keyframes or control points of its path. Linearly moving objects are unbounded.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (mut path, rotates): (Vec<Vec3D>, bool) = match &self.motion {
+            Motion::Linear { .. } => return None,
+            Motion::Keyframes {
+                translation,
+                rotation,
+            } => (
+                translation.control_points(),
+                rotation.control_points().iter().any(|r| !r.almost_zero()),
+            ),
+        };
+        if path.is_empty() {
+            path.push(Vec3D::default());
+        }
+        let bounds = self.object.bounding_box()?;
+        if !rotates {
+            // without rotation the box itself moves along
+            return Aabb::from_points(path.iter().flat_map(|&translation| {
+                [bounds.min, bounds.max].map(|corner| corner + translation)
+            }));
+        }
+        let reach = (0..8)
+            .map(|i| {
+                let corner = |bit: usize, min: f64, max: f64| if i & bit == 0 { min } else { max };
+                Vec3D::new(
+                    corner(1, bounds.min.x, bounds.max.x),
+                    corner(2, bounds.min.y, bounds.max.y),
+                    corner(4, bounds.min.z, bounds.max.z),
+                )
+                .norm()
+            })
+            .fold(0.0, f64::max);
+        Aabb::from_points(path).map(|path| path.pad(Vec3D::new(reach, reach, reach)))
+    }
+}
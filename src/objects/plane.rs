@@ -23,16 +23,22 @@ impl Plane {
         let v = fmod(point * e2, 1.0);
         (u, v)
     }
-}
 
-impl Hittable for Plane {
     // Solve for ray: p0 + t * p intersecting with plane: n * v = d * |n| where
     // n = normal to plane
     // d = distance to plane from origin
     // v = vector on the plane
     // results in solution t = (d * |n| - r0 * n) / (r * n)
     // requires r * n =/= 0 for (unique) solution to exist (ray is not parallel with the plane)
-    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData> {
+    //
+    // Skips hits on parts of the surface cut out by the material if cut_outs is set
+    fn first_hit(
+        &self,
+        ray: Ray,
+        t_min: f64,
+        t_max: f64,
+        cut_outs: bool,
+    ) -> Option<IntersectionData<'_>> {
         if (self.normal * ray.direction).abs() <= f64::EPSILON {
             None
         } else {
@@ -43,21 +49,43 @@ impl Hittable for Plane {
                 let normal = self.normal;
                 let front_face = ray.direction * normal < 0.0;
                 let (u, v) = self.point_to_uv(ray.at(t));
+                if let Some(phong_data) = self.phong_data.as_ref().filter(|_| cut_outs) {
+                    if phong_data.is_cut_out(u, v) {
+                        return None;
+                    }
+                }
                 Some(IntersectionData {
-                    ray: ray,
-                    t: t,
+                    ray,
+                    t,
                     normal: if front_face { normal } else { -normal },
+                    front_face,
                     phong_data: self.phong_data.as_ref(),
-                    u: u,
-                    v: v,
+                    u,
+                    v,
+                    tangent: None,
                 })
             } else {
                 None
             }
         }
     }
+}
+
+impl Hittable for Plane {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, true)
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, false)
+    }
 
     fn get_phong_data(&self) -> Option<&PhongModel> {
         self.phong_data.as_ref()
     }
+
+    // Rays enter the plane through its front face, so the half-space behind it is inside
+    fn contains(&self, point: Vec3D) -> Option<bool> {
+        Some(point * self.normal < self.distance * self.normal.norm())
+    }
 }
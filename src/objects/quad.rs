@@ -0,0 +1,87 @@
+use crate::materials::PhongModel;
+use crate::math::vector::Vec3D;
+use crate::objects::{aabb::Aabb, hittables::*, ray::Ray};
+
+use rand::{rngs::SmallRng, Rng};
+
+// Parallelogram spanned by edge_u and edge_v from corner, a rectangle if the edges are orthogonal.
+// The normal is edge_u x edge_v and uv runs from 0 to 1 along the edges.
+#[derive(Debug, Clone)]
+pub struct Quad {
+    pub corner: Vec3D,
+    pub edge_u: Vec3D,
+    pub edge_v: Vec3D,
+    pub phong_data: Option<PhongModel>,
+}
+
+impl Quad {
+    // Rectangle centered at center with the given width along right and height along up
+    pub fn rectangle(center: Vec3D, right: Vec3D, up: Vec3D, width: f64, height: f64) -> Self {
+        let edge_u = width * right.unit_vector();
+        let edge_v = height * up.perp(right).unit_vector();
+        Quad {
+            corner: center - 0.5 * edge_u - 0.5 * edge_v,
+            edge_u,
+            edge_v,
+            phong_data: None,
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.edge_u.cross(self.edge_v).norm()
+    }
+}
+
+impl Hittable for Quad {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let n = self.edge_u.cross(self.edge_v);
+        let denominator = ray.direction * n;
+        if denominator.abs() <= f64::EPSILON {
+            return None;
+        }
+        let t = (self.corner - ray.origin) * n / denominator;
+        // coordinates of the hit along the two edges
+        let offset = ray.at(t) - self.corner;
+        let w = n / n.norm2();
+        let u = w * offset.cross(self.edge_v);
+        let v = w * self.edge_u.cross(offset);
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        let mut candidates = [HitCandidate {
+            t,
+            normal: n.unit_vector(),
+            u,
+            v,
+        }];
+        closest_hit(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn sample_surface(&self, rng: &mut SmallRng, _from: Vec3D) -> Option<SurfaceSample> {
+        let (u, v) = (rng.gen::<f64>(), rng.gen::<f64>());
+        Some(SurfaceSample {
+            point: self.corner + u * self.edge_u + v * self.edge_v,
+            normal: self.edge_u.cross(self.edge_v).unit_vector(),
+            u,
+            v,
+            pdf: 1.0 / self.area(),
+        })
+    }
+
+    fn sample_pdf(&self, _from: Vec3D, _point: Vec3D) -> f64 {
+        1.0 / self.area()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points([
+            self.corner,
+            self.corner + self.edge_u,
+            self.corner + self.edge_v,
+            self.corner + self.edge_u + self.edge_v,
+        ])
+    }
+}
@@ -4,6 +4,7 @@ use crate::math::vector::Vec3D;
 pub struct Ray {
     pub origin: Vec3D,
     pub direction: Vec3D,
+    pub time: f64, // when the ray is sent out, moving objects are hit where they are then
 }
 
 impl Ray {
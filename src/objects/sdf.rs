@@ -0,0 +1,436 @@
+use crate::materials::PhongModel;
+use crate::math::vector::Vec3D;
+use crate::objects::{aabb::Aabb, hittables::*, ray::Ray};
+
+use std::sync::Arc;
+
+// Signed distance function, negative inside of the shape. Any closure taking a point and
+// returning a distance can be used as one.
+pub trait Sdf {
+    fn distance(&self, point: Vec3D) -> f64;
+
+    // Box the shape is contained in, None if it is unbounded or unknown
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+impl<F: Fn(Vec3D) -> f64> Sdf for F {
+    fn distance(&self, point: Vec3D) -> f64 {
+        self(point)
+    }
+}
+
+// Polynomial smooth minimum, blends a and b over distances up to k
+pub fn smooth_min(a: f64, b: f64, k: f64) -> f64 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b + (a - b) * h - k * h * (1.0 - h)
+}
+
+pub fn smooth_max(a: f64, b: f64, k: f64) -> f64 {
+    -smooth_min(-a, -b, k)
+}
+
+fn cube(center: Vec3D, half_extent: f64) -> Aabb {
+    let extent = Vec3D::new(half_extent, half_extent, half_extent);
+    Aabb::new(center - extent, center + extent)
+}
+
+pub struct SdfSphere {
+    pub center: Vec3D,
+    pub radius: f64,
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, point: Vec3D) -> f64 {
+        (point - self.center).norm() - self.radius
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(cube(self.center, self.radius))
+    }
+}
+
+// Axis aligned box with its edges rounded off by rounding
+pub struct SdfBox {
+    pub center: Vec3D,
+    pub half_extents: Vec3D,
+    pub rounding: f64,
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, point: Vec3D) -> f64 {
+        let p = point - self.center;
+        let r = self.rounding;
+        let q = Vec3D::new(
+            p.x.abs() - self.half_extents.x + r,
+            p.y.abs() - self.half_extents.y + r,
+            p.z.abs() - self.half_extents.z + r,
+        );
+        let outside = Vec3D::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).norm();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+        outside + inside - r
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.center - self.half_extents,
+            self.center + self.half_extents,
+        ))
+    }
+}
+
+// Torus around the y axis through center
+pub struct SdfTorus {
+    pub center: Vec3D,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, point: Vec3D) -> f64 {
+        let p = point - self.center;
+        let ring = f64::sqrt(p.x * p.x + p.z * p.z) - self.major_radius;
+        f64::sqrt(ring * ring + p.y * p.y) - self.minor_radius
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.major_radius + self.minor_radius;
+        Some(Aabb::new(
+            self.center - Vec3D::new(r, self.minor_radius, r),
+            self.center + Vec3D::new(r, self.minor_radius, r),
+        ))
+    }
+}
+
+pub struct SdfCapsule {
+    pub start: Vec3D,
+    pub end: Vec3D,
+    pub radius: f64,
+}
+
+impl Sdf for SdfCapsule {
+    fn distance(&self, point: Vec3D) -> f64 {
+        let axis = self.end - self.start;
+        let p = point - self.start;
+        let h = ((p * axis) / axis.norm2()).clamp(0.0, 1.0);
+        (p - h * axis).norm() - self.radius
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(cube(self.start, self.radius).union(cube(self.end, self.radius)))
+    }
+}
+
+// Closed cylinder along the y axis through center
+pub struct SdfCylinder {
+    pub center: Vec3D,
+    pub radius: f64,
+    pub half_height: f64,
+}
+
+impl Sdf for SdfCylinder {
+    fn distance(&self, point: Vec3D) -> f64 {
+        let p = point - self.center;
+        let dx = f64::sqrt(p.x * p.x + p.z * p.z) - self.radius;
+        let dy = p.y.abs() - self.half_height;
+        f64::min(dx.max(dy), 0.0) + f64::hypot(dx.max(0.0), dy.max(0.0))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3D::new(self.radius, self.half_height, self.radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+// The operators below blend their operands smoothly over `smoothness`, 0 gives sharp edges
+pub struct SdfUnion {
+    pub a: Arc<dyn Sdf + Send + Sync>,
+    pub b: Arc<dyn Sdf + Send + Sync>,
+    pub smoothness: f64,
+}
+
+impl Sdf for SdfUnion {
+    fn distance(&self, point: Vec3D) -> f64 {
+        smooth_min(
+            self.a.distance(point),
+            self.b.distance(point),
+            self.smoothness,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (a, b) = (self.a.bounding_box()?, self.b.bounding_box()?);
+        // the smooth blend can bulge out a little between the shapes
+        let margin = 0.25 * self.smoothness;
+        Some(a.union(b).pad(Vec3D::new(margin, margin, margin)))
+    }
+}
+
+pub struct SdfIntersection {
+    pub a: Arc<dyn Sdf + Send + Sync>,
+    pub b: Arc<dyn Sdf + Send + Sync>,
+    pub smoothness: f64,
+}
+
+impl Sdf for SdfIntersection {
+    fn distance(&self, point: Vec3D) -> f64 {
+        smooth_max(
+            self.a.distance(point),
+            self.b.distance(point),
+            self.smoothness,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match (self.a.bounding_box(), self.b.bounding_box()) {
+            (Some(a), Some(b)) => Some(a.intersection(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+// a minus b
+pub struct SdfDifference {
+    pub a: Arc<dyn Sdf + Send + Sync>,
+    pub b: Arc<dyn Sdf + Send + Sync>,
+    pub smoothness: f64,
+}
+
+impl Sdf for SdfDifference {
+    fn distance(&self, point: Vec3D) -> f64 {
+        smooth_max(
+            self.a.distance(point),
+            -self.b.distance(point),
+            self.smoothness,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.a.bounding_box()
+    }
+}
+
+// Morphs from a (factor 0) to b (factor 1)
+pub struct SdfBlend {
+    pub a: Arc<dyn Sdf + Send + Sync>,
+    pub b: Arc<dyn Sdf + Send + Sync>,
+    pub factor: f64,
+}
+
+impl Sdf for SdfBlend {
+    fn distance(&self, point: Vec3D) -> f64 {
+        let (a, b) = (self.a.distance(point), self.b.distance(point));
+        a + (b - a) * self.factor
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.a.bounding_box()?.union(self.b.bounding_box()?))
+    }
+}
+
+// Repeats the cell around the origin with the given period along each axis, a period of
+// 0 leaves that axis alone. The shape should fit in its cell for the distances to stay exact.
+pub struct SdfRepeat {
+    pub sdf: Arc<dyn Sdf + Send + Sync>,
+    pub period: Vec3D,
+}
+
+impl Sdf for SdfRepeat {
+    fn distance(&self, point: Vec3D) -> f64 {
+        let wrap = |x: f64, period: f64| {
+            if period > 0.0 {
+                x - period * (x / period).round()
+            } else {
+                x
+            }
+        };
+        self.sdf.distance(Vec3D::new(
+            wrap(point.x, self.period.x),
+            wrap(point.y, self.period.y),
+            wrap(point.z, self.period.z),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.period.x > 0.0 || self.period.y > 0.0 || self.period.z > 0.0 {
+            None
+        } else {
+            self.sdf.bounding_box()
+        }
+    }
+}
+
+// Twists the shape around the y axis by rate radians per unit of height. This stretches
+// distances, so objects using it need a step_scale below 1.
+pub struct SdfTwist {
+    pub sdf: Arc<dyn Sdf + Send + Sync>,
+    pub rate: f64,
+}
+
+impl Sdf for SdfTwist {
+    fn distance(&self, point: Vec3D) -> f64 {
+        let (sin, cos) = (self.rate * point.y).sin_cos();
+        self.sdf.distance(Vec3D::new(
+            cos * point.x - sin * point.z,
+            point.y,
+            sin * point.x + cos * point.z,
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // any rotation of the shape around the y axis stays within this box
+        let aabb = self.sdf.bounding_box()?;
+        let x = aabb.min.x.abs().max(aabb.max.x.abs());
+        let z = aabb.min.z.abs().max(aabb.max.z.abs());
+        let r = f64::hypot(x, z);
+        Some(Aabb::new(
+            Vec3D::new(-r, aabb.min.y, -r),
+            Vec3D::new(r, aabb.max.y, r),
+        ))
+    }
+}
+
+// Renders the zero level set of an Sdf by sphere tracing. u, v are mapped from the normal
+// like on a sphere.
+pub struct SdfObject {
+    pub sdf: Arc<dyn Sdf + Send + Sync>,
+    pub max_steps: u32,
+    pub epsilon: f64,      // distance at which the surface counts as hit
+    pub step_scale: f64,   // fraction of the distance bound that is stepped, 1 for exact SDFs
+    pub max_distance: f64, // how far unbounded shapes are marched
+    pub phong_data: Option<PhongModel>,
+}
+
+impl SdfObject {
+    pub fn new(sdf: Arc<dyn Sdf + Send + Sync>) -> Self {
+        SdfObject {
+            sdf,
+            max_steps: 256,
+            epsilon: 1e-5,
+            step_scale: 1.0,
+            max_distance: 100.0,
+            phong_data: None,
+        }
+    }
+
+    // Outward normal from the central difference gradient
+    pub fn get_normal_at(&self, point: Vec3D) -> Vec3D {
+        let h = 1e-5;
+        let d =
+            |offset: Vec3D| self.sdf.distance(point + offset) - self.sdf.distance(point - offset);
+        Vec3D::new(
+            d(Vec3D::new(h, 0.0, 0.0)),
+            d(Vec3D::new(0.0, h, 0.0)),
+            d(Vec3D::new(0.0, 0.0, h)),
+        )
+        .unit_vector()
+    }
+
+    fn point_to_uv(normal: Vec3D) -> (f64, f64) {
+        use std::f64::consts::PI;
+        let u = f64::atan2(normal.x, normal.z) / (2.0 * PI) + 0.5;
+        let v = f64::asin(normal.y.clamp(-1.0, 1.0)) / PI + 0.5;
+        (u, 1.0 - v)
+    }
+}
+
+impl SdfObject {
+    // Steps along the ray by the distance bound until the distance falls below epsilon or
+    // changes sign. Rays starting inside of the shape march to where they leave it.
+    fn march(&self, ray: Ray, t_start: f64, t_end: f64) -> Option<f64> {
+        let scale = ray.direction.norm();
+        let mut t = t_start;
+        let mut distance = self.sdf.distance(ray.at(t));
+        // a ray starting on the surface is on the side it is heading into
+        let mut escaped = distance.abs() >= self.epsilon;
+        let outside = if escaped {
+            distance > 0.0
+        } else {
+            self.get_normal_at(ray.at(t)) * ray.direction > 0.0
+        };
+        for _ in 0..self.max_steps {
+            let t_prev = t;
+            t += f64::max(distance.abs() * self.step_scale, self.epsilon) / scale;
+            if t > t_end {
+                return None;
+            }
+            distance = self.sdf.distance(ray.at(t));
+            if escaped && (distance > 0.0) != outside {
+                // stepped through the surface, bisect back onto it
+                let (mut lo, mut hi) = (t_prev, t);
+                for _ in 0..16 {
+                    let mid = 0.5 * (lo + hi);
+                    if (self.sdf.distance(ray.at(mid)) > 0.0) == outside {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                return Some(hi);
+            }
+            if distance.abs() >= self.epsilon {
+                escaped = true;
+            } else if escaped {
+                return Some(t);
+            }
+        }
+        None
+    }
+
+    // First crossing of the surface, skipping those cut out by the material if cut_outs is set
+    fn first_hit(
+        &self,
+        ray: Ray,
+        t_min: f64,
+        t_max: f64,
+        cut_outs: bool,
+    ) -> Option<IntersectionData<'_>> {
+        let (mut t_start, t_end) = match self.sdf.bounding_box() {
+            // padded so marching never starts right on a surface touching the box
+            Some(aabb) => aabb
+                .pad(Vec3D::new(1e-3, 1e-3, 1e-3))
+                .clip(ray, t_min, t_max)?,
+            None => (t_min, t_max),
+        };
+        let t_end = t_end.min(t_min + self.max_distance / ray.direction.norm());
+        loop {
+            let t = self.march(ray, t_start, t_end)?;
+            let normal = self.get_normal_at(ray.at(t));
+            let (u, v) = SdfObject::point_to_uv(normal);
+            let mut candidates = [HitCandidate { t, normal, u, v }];
+            let phong_data = self.phong_data.as_ref();
+            let hit = if cut_outs {
+                closest_hit(ray, t_min, t_max, &mut candidates, phong_data)
+            } else {
+                closest_crossing(ray, t_min, t_max, &mut candidates, phong_data)
+            };
+            if hit.is_some() {
+                return hit;
+            }
+            // cut out, continue behind the surface
+            t_start = t;
+        }
+    }
+}
+
+impl Hittable for SdfObject {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, true)
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, false)
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.sdf.bounding_box()
+    }
+}
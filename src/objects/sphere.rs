@@ -1,6 +1,9 @@
 use crate::materials::PhongModel;
+use crate::math::sampling::uniform_hemisphere;
 use crate::math::vector::Vec3D;
-use crate::objects::{hittables::*, ray::Ray};
+use crate::objects::{aabb::Aabb, hittables::*, ray::Ray};
+
+use rand::rngs::SmallRng;
 
 #[derive(Debug, Clone)]
 pub struct Sphere {
@@ -16,9 +19,7 @@ impl Sphere {
         let v = f64::asin(point.y) / PI + 0.5;
         (u, 1.0 - v)
     }
-}
 
-impl Hittable for Sphere {
     // Solve for ray: p0 + t * p intersecting with sphere: |x-x0| = r
     // results in solving a quadratic formula at^2 + bt + c = 0 with
     // a = p^2
@@ -28,7 +29,15 @@ impl Hittable for Sphere {
     // returns the solution closest to the origin of the ray
     // (assuming the ray's origin is outside of the sphere)
     // (assuming the sphere is in the +-direction of the ray)
-    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData> {
+    //
+    // Skips hits on parts of the surface cut out by the material if cut_outs is set
+    fn first_hit(
+        &self,
+        ray: Ray,
+        t_min: f64,
+        t_max: f64,
+        cut_outs: bool,
+    ) -> Option<IntersectionData<'_>> {
         let a = ray.direction.norm2(); // a = r^2
         let b_half = ray.direction * (ray.origin - self.center);
         let c = self.center.norm2() + ray.origin.norm2()
@@ -40,11 +49,23 @@ impl Hittable for Sphere {
             let sqrt_discriminant = discriminant.sqrt();
             let root1 = (-b_half - sqrt_discriminant) / a;
             let root2 = (-b_half + sqrt_discriminant) / a;
-            let is_within_bounds = |t| -> bool { t_min < t && t < t_max };
+            // roots outside of the bounds or on cut out parts of the surface are skipped
+            let is_hit = |t| -> bool {
+                if !(t_min < t && t < t_max) {
+                    return false;
+                }
+                match self.phong_data.as_ref().filter(|_| cut_outs) {
+                    Some(phong_data) => {
+                        let (u, v) = self.point_to_uv((ray.at(t) - self.center) / self.radius);
+                        !phong_data.is_cut_out(u, v)
+                    }
+                    None => true,
+                }
+            };
             let mut root = root1; // root1 <= root2
-            if !is_within_bounds(root1) {
+            if !is_hit(root1) {
                 root = root2;
-                if !is_within_bounds(root2) {
+                if !is_hit(root2) {
                     return None;
                 }
             }
@@ -53,20 +74,60 @@ impl Hittable for Sphere {
             let normal: Vec3D = (p - self.center) / self.radius;
             let front_face: bool = ray.direction * normal < 0.0;
             let (u, v) = self.point_to_uv(normal);
-            return Some(IntersectionData {
-                ray: ray,
+            Some(IntersectionData {
+                ray,
                 t: root,
                 normal: if front_face { normal } else { -normal },
+                front_face,
                 phong_data: self.phong_data.as_ref(),
-                u: u,
-                v: v,
-            });
+                u,
+                v,
+                tangent: None,
+            })
         } else {
             None
         }
     }
+}
+
+impl Hittable for Sphere {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, true)
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        self.first_hit(ray, t_min, t_max, false)
+    }
 
     fn get_phong_data(&self) -> Option<&PhongModel> {
         self.phong_data.as_ref()
     }
+
+    // Only the hemisphere facing `from` can be visible, so only that half is sampled
+    fn sample_surface(&self, rng: &mut SmallRng, from: Vec3D) -> Option<SurfaceSample> {
+        use std::f64::consts::PI;
+        let normal = uniform_hemisphere(rng, (from - self.center).unit_vector());
+        let (u, v) = self.point_to_uv(normal);
+        Some(SurfaceSample {
+            point: self.center + self.radius * normal,
+            normal,
+            u,
+            v,
+            pdf: 1.0 / (2.0 * PI * self.radius * self.radius),
+        })
+    }
+
+    fn sample_pdf(&self, from: Vec3D, point: Vec3D) -> f64 {
+        use std::f64::consts::PI;
+        if (point - self.center) * (from - self.center) > 0.0 {
+            1.0 / (2.0 * PI * self.radius * self.radius)
+        } else {
+            0.0
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3D::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
 }
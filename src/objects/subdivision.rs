@@ -0,0 +1,481 @@
+use crate::math::vector::Vec3D;
+use crate::objects::mesh::{Mesh, ObjFile};
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubdivisionScheme {
+    Loop,         // triangles, faces are triangulated first
+    CatmullClark, // any polygons, every level produces quads
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+// Index of the uv halfway between the uvs a and b, added once for every pair
+fn midpoint_uv(
+    uvs: &mut Vec<(f64, f64)>,
+    midpoints: &mut HashMap<(usize, usize), usize>,
+    a: usize,
+    b: usize,
+) -> usize {
+    *midpoints.entry(edge_key(a, b)).or_insert_with(|| {
+        let ((u_a, v_a), (u_b, v_b)) = (uvs[a], uvs[b]);
+        uvs.push((0.5 * (u_a + u_b), 0.5 * (v_a + v_b)));
+        uvs.len() - 1
+    })
+}
+
+// Faces sharing an edge. Edges are kept in a BTreeMap so the new vertices are numbered the
+// same way on every run.
+#[derive(Debug, Default)]
+struct EdgeInfo {
+    faces: Vec<usize>,
+}
+
+// Polygon mesh with shared vertices, the input to subdivision. Edges can be marked as creases
+// with a sharpness: edges with sharpness n stay sharp for n levels of subdivision, fractional
+// values blend between sharp and smooth. Boundary edges are always sharp.
+// Texture coordinates are given per face corner, so faces meeting at a seam can use different
+// ones for the same vertex. They are interpolated linearly, not smoothed.
+#[derive(Debug, Clone, Default)]
+pub struct PolygonMesh {
+    pub vertices: Vec<Vec3D>,
+    pub faces: Vec<Vec<usize>>,
+    pub creases: HashMap<(usize, usize), f64>,
+    pub uvs: Vec<(f64, f64)>,
+    pub face_uvs: Vec<Vec<usize>>, // indices into uvs for the corners of faces, or empty
+}
+
+impl PolygonMesh {
+    // Vertex positions, texture coordinates and faces of an OBJ file, without triangulating.
+    // Normals are left out, they don't fit the subdivided surface. In files with texture
+    // coordinates, corners without one get (0, 0).
+    pub fn from_obj<P: AsRef<Path>>(filename: P) -> Result<Self, Box<dyn Error>> {
+        let mut obj = ObjFile::load(filename)?;
+        let corners = || obj.faces.iter().flatten();
+        let face_uvs = if corners().any(|corner| corner[1] != ObjFile::MISSING) {
+            let missing = obj.uvs.len();
+            obj.uvs.push((0.0, 0.0));
+            obj.faces
+                .iter()
+                .map(|face| {
+                    face.iter()
+                        .map(|&[_, vt, _]| match vt {
+                            ObjFile::MISSING => missing,
+                            vt => vt as usize,
+                        })
+                        .collect()
+                })
+                .collect()
+        } else {
+            obj.uvs.clear();
+            vec![]
+        };
+        Ok(PolygonMesh {
+            vertices: obj.positions,
+            faces: obj
+                .faces
+                .iter()
+                .map(|face| face.iter().map(|&[v, _, _]| v as usize).collect())
+                .collect(),
+            creases: HashMap::new(),
+            uvs: obj.uvs,
+            face_uvs,
+        })
+    }
+
+    pub fn set_crease(&mut self, a: usize, b: usize, sharpness: f64) {
+        self.creases.insert(edge_key(a, b), sharpness);
+    }
+
+    fn get_sharpness(
+        &self,
+        key: (usize, usize),
+        edges: &BTreeMap<(usize, usize), EdgeInfo>,
+    ) -> f64 {
+        // boundary and non-manifold edges are kept sharp
+        if edges[&key].faces.len() != 2 {
+            f64::INFINITY
+        } else {
+            self.creases.get(&key).copied().unwrap_or(0.0)
+        }
+    }
+
+    fn get_edges(&self) -> BTreeMap<(usize, usize), EdgeInfo> {
+        let mut edges: BTreeMap<(usize, usize), EdgeInfo> = BTreeMap::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            for i in 0..face.len() {
+                let key = edge_key(face[i], face[(i + 1) % face.len()]);
+                edges.entry(key).or_default().faces.push(f);
+            }
+        }
+        edges
+    }
+
+    // Edges around every vertex
+    fn get_vertex_edges(
+        &self,
+        edges: &BTreeMap<(usize, usize), EdgeInfo>,
+    ) -> Vec<Vec<(usize, usize)>> {
+        let mut vertex_edges = vec![Vec::new(); self.vertices.len()];
+        for &(a, b) in edges.keys() {
+            vertex_edges[a].push((a, b));
+            vertex_edges[b].push((a, b));
+        }
+        vertex_edges
+    }
+
+    // New position of an original vertex. With fewer than two sharp edges the smooth rule is
+    // used, with two the vertex moves along the crease and with more it is a corner. Boundary
+    // vertices with only two edges are corners as well. Semi-sharp creases blend the result
+    // with the smooth rule by their average sharpness.
+    fn get_vertex_point<F: Fn() -> Vec3D>(
+        &self,
+        v: usize,
+        incident: &[(usize, usize)],
+        edges: &BTreeMap<(usize, usize), EdgeInfo>,
+        smooth: F,
+        crease_weight: f64, // weight of the vertex itself in the crease rule
+    ) -> Vec3D {
+        let position = self.vertices[v];
+        // vertices that no face uses stay where they are
+        if incident.is_empty() {
+            return position;
+        }
+        let sharp: Vec<((usize, usize), f64)> = incident
+            .iter()
+            .map(|&key| (key, self.get_sharpness(key, edges)))
+            .filter(|&(_, sharpness)| sharpness > 0.0)
+            .collect();
+        let is_boundary = sharp.iter().any(|&(_, sharpness)| sharpness.is_infinite());
+        let sharp_point = match sharp.len() {
+            0 | 1 => return smooth(),
+            2 if !(is_boundary && incident.len() == 2) => {
+                let other = |(a, b): (usize, usize)| if a == v { b } else { a };
+                let neighbours =
+                    self.vertices[other(sharp[0].0)] + self.vertices[other(sharp[1].0)];
+                crease_weight * position + 0.5 * (1.0 - crease_weight) * neighbours
+            }
+            _ => position,
+        };
+        let sharpness = sharp.iter().map(|&(_, s)| s).sum::<f64>() / sharp.len() as f64;
+        if sharpness >= 1.0 {
+            sharp_point
+        } else {
+            let smooth_point = smooth();
+            smooth_point + sharpness * (sharp_point - smooth_point)
+        }
+    }
+
+    // Sharpness of the edges created by splitting an edge, one level smoother
+    fn child_creases(
+        &self,
+        key: (usize, usize),
+        children: [(usize, usize); 2],
+        creases: &mut HashMap<(usize, usize), f64>,
+    ) {
+        if let Some(sharpness) = self.creases.get(&key) {
+            if *sharpness > 1.0 {
+                for (a, b) in children {
+                    creases.insert(edge_key(a, b), sharpness - 1.0);
+                }
+            }
+        }
+    }
+
+    pub fn subdivide(&self, scheme: SubdivisionScheme) -> Self {
+        match scheme {
+            SubdivisionScheme::Loop => self.triangulated().subdivide_loop(),
+            SubdivisionScheme::CatmullClark => self.subdivide_catmull_clark(),
+        }
+    }
+
+    pub fn subdivide_levels(&self, scheme: SubdivisionScheme, levels: u32) -> Self {
+        let mut mesh = self.clone();
+        for _ in 0..levels {
+            mesh = mesh.subdivide(scheme);
+        }
+        mesh
+    }
+
+    // Splits polygons into triangle fans. Triangles repeating a vertex have no area and would
+    // leave edges without a vertex opposite of them, they are dropped.
+    pub fn triangulated(&self) -> Self {
+        let mut faces = Vec::with_capacity(self.faces.len());
+        let mut face_uvs = Vec::with_capacity(self.face_uvs.len());
+        for (f, face) in self.faces.iter().enumerate() {
+            for i in 1..face.len() - 1 {
+                let (a, b, c) = (face[0], face[i], face[i + 1]);
+                if a == b || b == c || c == a {
+                    continue;
+                }
+                faces.push(vec![a, b, c]);
+                if let Some(uvs) = self.face_uvs.get(f) {
+                    face_uvs.push(vec![uvs[0], uvs[i], uvs[i + 1]]);
+                }
+            }
+        }
+        PolygonMesh {
+            vertices: self.vertices.clone(),
+            faces,
+            creases: self.creases.clone(),
+            uvs: self.uvs.clone(),
+            face_uvs,
+        }
+    }
+
+    fn subdivide_catmull_clark(&self) -> Self {
+        let edges = self.get_edges();
+        let vertex_edges = self.get_vertex_edges(&edges);
+        let n_vertices = self.vertices.len();
+
+        let face_points: Vec<Vec3D> = self
+            .faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .fold(Vec3D::default(), |sum, &v| sum + self.vertices[v])
+                    / face.len() as f64
+            })
+            .collect();
+
+        // new vertices: original vertices, then edge points, then face points
+        let mut edge_index: HashMap<(usize, usize), usize> = HashMap::with_capacity(edges.len());
+        let mut vertices = Vec::with_capacity(n_vertices + edges.len() + self.faces.len());
+        vertices.resize(n_vertices, Vec3D::default());
+        for (&key, info) in edges.iter() {
+            let midpoint = 0.5 * (self.vertices[key.0] + self.vertices[key.1]);
+            let sharpness = self.get_sharpness(key, &edges);
+            let point = if sharpness >= 1.0 {
+                midpoint
+            } else {
+                let faces = info
+                    .faces
+                    .iter()
+                    .fold(Vec3D::default(), |sum, &f| sum + face_points[f]);
+                let smooth = 0.5 * midpoint + 0.25 * faces;
+                smooth + sharpness * (midpoint - smooth)
+            };
+            edge_index.insert(key, vertices.len());
+            vertices.push(point);
+        }
+        let face_offset = vertices.len();
+        vertices.extend(face_points.iter().copied());
+
+        let mut vertex_faces = vec![Vec::new(); n_vertices];
+        for (f, face) in self.faces.iter().enumerate() {
+            for &v in face {
+                vertex_faces[v].push(f);
+            }
+        }
+        for v in 0..n_vertices {
+            let incident = &vertex_edges[v];
+            let smooth = || {
+                let n = incident.len() as f64;
+                let q = vertex_faces[v]
+                    .iter()
+                    .fold(Vec3D::default(), |sum, &f| sum + face_points[f])
+                    / vertex_faces[v].len() as f64;
+                let r = incident.iter().fold(Vec3D::default(), |sum, &(a, b)| {
+                    sum + 0.5 * (self.vertices[a] + self.vertices[b])
+                }) / n;
+                (q + 2.0 * r + (n - 3.0) * self.vertices[v]) / n
+            };
+            vertices[v] = self.get_vertex_point(v, incident, &edges, smooth, 0.75);
+        }
+
+        let mut faces = Vec::with_capacity(4 * self.faces.len());
+        let mut creases = HashMap::new();
+        for (f, face) in self.faces.iter().enumerate() {
+            let n = face.len();
+            for i in 0..n {
+                let (prev, v, next) = (face[(i + n - 1) % n], face[i], face[(i + 1) % n]);
+                faces.push(vec![
+                    v,
+                    edge_index[&edge_key(v, next)],
+                    face_offset + f,
+                    edge_index[&edge_key(prev, v)],
+                ]);
+            }
+        }
+
+        // the uvs of the face points are the averages of the corner uvs
+        let mut uvs = self.uvs.clone();
+        let mut midpoints = HashMap::new();
+        let mut face_uvs = Vec::with_capacity(4 * self.face_uvs.len());
+        for corners in &self.face_uvs {
+            let n = corners.len();
+            let (u, v) = corners.iter().fold((0.0, 0.0), |(u, v), &uv| {
+                (u + self.uvs[uv].0, v + self.uvs[uv].1)
+            });
+            uvs.push((u / n as f64, v / n as f64));
+            let center = uvs.len() - 1;
+            for i in 0..n {
+                let (prev, uv, next) = (corners[(i + n - 1) % n], corners[i], corners[(i + 1) % n]);
+                face_uvs.push(vec![
+                    uv,
+                    midpoint_uv(&mut uvs, &mut midpoints, uv, next),
+                    center,
+                    midpoint_uv(&mut uvs, &mut midpoints, prev, uv),
+                ]);
+            }
+        }
+        for &key in edges.keys() {
+            let e = edge_index[&key];
+            self.child_creases(key, [(key.0, e), (e, key.1)], &mut creases);
+        }
+        PolygonMesh {
+            vertices,
+            faces,
+            creases,
+            uvs,
+            face_uvs,
+        }
+    }
+
+    fn subdivide_loop(&self) -> Self {
+        let edges = self.get_edges();
+        let vertex_edges = self.get_vertex_edges(&edges);
+        let n_vertices = self.vertices.len();
+
+        let mut edge_index: HashMap<(usize, usize), usize> = HashMap::with_capacity(edges.len());
+        let mut vertices = Vec::with_capacity(n_vertices + edges.len());
+        vertices.resize(n_vertices, Vec3D::default());
+        for (&key, info) in edges.iter() {
+            let midpoint = 0.5 * (self.vertices[key.0] + self.vertices[key.1]);
+            let sharpness = self.get_sharpness(key, &edges);
+            let point = if sharpness >= 1.0 {
+                midpoint
+            } else {
+                // the vertices opposite of the edge in both triangles
+                let opposite = info.faces.iter().fold(Vec3D::default(), |sum, &f| {
+                    let corner = self.faces[f].iter().find(|&&v| v != key.0 && v != key.1);
+                    sum + self.vertices[*corner.unwrap()]
+                });
+                let smooth = 0.75 * midpoint + 0.125 * opposite;
+                smooth + sharpness * (midpoint - smooth)
+            };
+            edge_index.insert(key, vertices.len());
+            vertices.push(point);
+        }
+
+        for v in 0..n_vertices {
+            let incident = &vertex_edges[v];
+            let smooth = || {
+                let n = incident.len() as f64;
+                let beta = if incident.len() == 3 {
+                    3.0 / 16.0
+                } else {
+                    3.0 / (8.0 * n)
+                };
+                let neighbours = incident.iter().fold(Vec3D::default(), |sum, &(a, b)| {
+                    sum + self.vertices[if a == v { b } else { a }]
+                });
+                (1.0 - n * beta) * self.vertices[v] + beta * neighbours
+            };
+            vertices[v] = self.get_vertex_point(v, incident, &edges, smooth, 0.75);
+        }
+
+        let mut faces = Vec::with_capacity(4 * self.faces.len());
+        for face in &self.faces {
+            let (a, b, c) = (face[0], face[1], face[2]);
+            let (ab, bc, ca) = (
+                edge_index[&edge_key(a, b)],
+                edge_index[&edge_key(b, c)],
+                edge_index[&edge_key(c, a)],
+            );
+            faces.push(vec![a, ab, ca]);
+            faces.push(vec![ab, b, bc]);
+            faces.push(vec![ca, bc, c]);
+            faces.push(vec![ab, bc, ca]);
+        }
+        let mut uvs = self.uvs.clone();
+        let mut midpoints = HashMap::new();
+        let mut face_uvs = Vec::with_capacity(4 * self.face_uvs.len());
+        for corners in &self.face_uvs {
+            let (a, b, c) = (corners[0], corners[1], corners[2]);
+            let (ab, bc, ca) = (
+                midpoint_uv(&mut uvs, &mut midpoints, a, b),
+                midpoint_uv(&mut uvs, &mut midpoints, b, c),
+                midpoint_uv(&mut uvs, &mut midpoints, c, a),
+            );
+            face_uvs.push(vec![a, ab, ca]);
+            face_uvs.push(vec![ab, b, bc]);
+            face_uvs.push(vec![ca, bc, c]);
+            face_uvs.push(vec![ab, bc, ca]);
+        }
+        let mut creases = HashMap::new();
+        for &key in edges.keys() {
+            let e = edge_index[&key];
+            self.child_creases(key, [(key.0, e), (e, key.1)], &mut creases);
+        }
+        PolygonMesh {
+            vertices,
+            faces,
+            creases,
+            uvs,
+            face_uvs,
+        }
+    }
+
+    // Triangulates the faces into a Mesh with smooth vertex normals, averaged from the
+    // normals of the faces around each vertex weighted by their area. Vertices with several
+    // texture coordinates are split into one for each, sharing the normal.
+    pub fn to_mesh(&self) -> Mesh {
+        let triangulated = self.triangulated();
+        let mut normals = vec![Vec3D::default(); self.vertices.len()];
+        for face in &triangulated.faces {
+            let (a, b, c) = (
+                self.vertices[face[0]],
+                self.vertices[face[1]],
+                self.vertices[face[2]],
+            );
+            let area_normal = (b - a).cross(c - a);
+            for &v in face {
+                normals[v] += area_normal;
+            }
+        }
+        let normals: Vec<Vec3D> = normals.iter().map(|normal| normal.unit_vector()).collect();
+        if triangulated.face_uvs.is_empty() {
+            let indices = triangulated
+                .faces
+                .iter()
+                .map(|face| [face[0] as u32, face[1] as u32, face[2] as u32])
+                .collect();
+            return Mesh::indexed(self.vertices.clone(), normals, vec![], indices);
+        }
+        let mut vertices: HashMap<(usize, usize), u32> = HashMap::new();
+        let (mut positions, mut vertex_normals, mut uvs) = (vec![], vec![], vec![]);
+        let indices = triangulated
+            .faces
+            .iter()
+            .zip(&triangulated.face_uvs)
+            .map(|(face, corners)| {
+                [0, 1, 2].map(|i| {
+                    *vertices.entry((face[i], corners[i])).or_insert_with(|| {
+                        positions.push(self.vertices[face[i]]);
+                        vertex_normals.push(normals[face[i]]);
+                        uvs.push(self.uvs[corners[i]]);
+                        positions.len() as u32 - 1
+                    })
+                })
+            })
+            .collect();
+        Mesh::indexed(positions, vertex_normals, uvs, indices)
+    }
+}
+
+// Loads an OBJ file and subdivides it levels times before triangulating it
+pub fn load_subdivided<P: AsRef<Path>>(
+    filename: P,
+    scheme: SubdivisionScheme,
+    levels: u32,
+) -> Result<Mesh, Box<dyn Error>> {
+    Ok(PolygonMesh::from_obj(filename)?
+        .subdivide_levels(scheme, levels)
+        .to_mesh())
+}
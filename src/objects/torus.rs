@@ -0,0 +1,93 @@
+use crate::materials::PhongModel;
+use crate::math::polynomial::solve_quartic;
+use crate::math::sampling::orthonormal_basis;
+use crate::math::vector::Vec3D;
+use crate::objects::{
+    aabb::{disk_bounds, Aabb},
+    disk::polar_angle,
+    hittables::*,
+    ray::Ray,
+};
+
+// Ring around center in the plane orthogonal to the unit axis. u runs around the ring and
+// v around the tube.
+#[derive(Debug, Clone)]
+pub struct Torus {
+    pub center: Vec3D,
+    pub axis: Vec3D,
+    pub major_radius: f64, // from the center to the middle of the tube
+    pub minor_radius: f64, // of the tube
+    pub phong_data: Option<PhongModel>,
+}
+
+impl Torus {
+    // In torus space with the axis along z the surface is
+    // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2)
+    // and inserting the ray p = o + t * d gives a quartic in t, its roots are the crossings of
+    // the ray with the surface
+    fn candidates(&self, ray: Ray) -> Vec<HitCandidate> {
+        let (tangent, bitangent) = orthonormal_basis(self.axis);
+        let to_local = |v: Vec3D| Vec3D::new(v * tangent, v * bitangent, v * self.axis);
+        // solving from a point near the torus keeps the coefficients well conditioned
+        let scale = ray.direction.norm();
+        let direction = to_local(ray.direction) / scale;
+        let offset = f64::max(
+            (self.center - ray.origin) * ray.direction / scale
+                - self.major_radius
+                - self.minor_radius,
+            0.0,
+        );
+        let origin = to_local(ray.origin - self.center) + offset * direction;
+
+        let r2 = self.major_radius * self.major_radius;
+        let e = origin * direction;
+        let f = origin.norm2() + r2 - self.minor_radius * self.minor_radius;
+        let roots = solve_quartic(
+            1.0,
+            4.0 * e,
+            2.0 * f + 4.0 * e * e
+                - 4.0 * r2 * (direction.x * direction.x + direction.y * direction.y),
+            4.0 * e * f - 8.0 * r2 * (origin.x * direction.x + origin.y * direction.y),
+            f * f - 4.0 * r2 * (origin.x * origin.x + origin.y * origin.y),
+        );
+
+        roots
+            .into_iter()
+            .map(|s| {
+                let p = origin + s * direction;
+                let ring_distance = f64::sqrt(p.x * p.x + p.y * p.y);
+                let ring_point = Vec3D::new(p.x, p.y, 0.0) * (self.major_radius / ring_distance);
+                let local_normal = (p - ring_point) / self.minor_radius;
+                HitCandidate {
+                    t: (s + offset) / scale,
+                    normal: local_normal.x * tangent
+                        + local_normal.y * bitangent
+                        + local_normal.z * self.axis,
+                    u: polar_angle(p.x, p.y),
+                    v: polar_angle(ring_distance - self.major_radius, p.z),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Hittable for Torus {
+    fn intersect(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_hit(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn intersect_solid(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<IntersectionData<'_>> {
+        let mut candidates = self.candidates(ray);
+        closest_crossing(ray, t_min, t_max, &mut candidates, self.phong_data.as_ref())
+    }
+
+    fn get_phong_data(&self) -> Option<&PhongModel> {
+        self.phong_data.as_ref()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let margin = Vec3D::new(self.minor_radius, self.minor_radius, self.minor_radius);
+        Some(disk_bounds(self.center, self.axis, self.major_radius).pad(margin))
+    }
+}
@@ -12,8 +12,13 @@ pub struct Triangle {
 
 impl Triangle {
     pub fn get_normal_at(&self, point: Vec3D) -> Vec3D {
+        let (u, v) = self.point_to_uv(point);
+        self.interpolate_normal(u, v)
+    }
+
+    // Shading normal at barycentric coordinates u, v, 1 - u - v
+    pub fn interpolate_normal(&self, u: f64, v: f64) -> Vec3D {
         if let Some(vertex_normals) = self.normal {
-            let (u, v) = self.point_to_uv(point);
             u * vertex_normals[0] + v * vertex_normals[1] + (1.0 - u - v) * vertex_normals[2]
         } else {
             self.get_plane_normal()
@@ -26,30 +31,88 @@ impl Triangle {
         Vec3D::cross(ab, ac).unit_vector()
     }
 
-    pub fn get_intersection(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<f64> {
-        let normal = self.get_plane_normal();
-        let distance = self.vert_a * normal;
-        if (normal * ray.direction).abs() <= f64::EPSILON {
-            None
+    pub fn area(&self) -> f64 {
+        0.5 * Vec3D::cross(self.vert_b - self.vert_a, self.vert_c - self.vert_a).norm()
+    }
+
+    // Point with barycentric coordinates u, v, 1 - u - v (matching point_to_uv)
+    pub fn point_at(&self, u: f64, v: f64) -> Vec3D {
+        u * self.vert_a + v * self.vert_b + (1.0 - u - v) * self.vert_c
+    }
+
+    // Texture coordinates at barycentric coordinates u, v, 1 - u - v. Without vertex uvs
+    // the barycentric coordinates are used directly.
+    pub fn interpolate_uv(&self, u: f64, v: f64) -> (f64, f64) {
+        if let Some([uv_a, uv_b, uv_c]) = self.uv {
+            let w = 1.0 - u - v;
+            // OBJ texture coordinates start at the bottom of the image
+            (
+                u * uv_a.0 + v * uv_b.0 + w * uv_c.0,
+                1.0 - (u * uv_a.1 + v * uv_b.1 + w * uv_c.1),
+            )
+        } else {
+            (u, v)
+        }
+    }
+
+    pub fn get_uv_at(&self, point: Vec3D) -> (f64, f64) {
+        let (u, v) = self.point_to_uv(point);
+        self.interpolate_uv(u, v)
+    }
+
+    // Watertight ray-triangle test (Woop, Benthin and Wald). Like Moller-Trumbore it gives the
+    // barycentric coordinates of the hit directly, but the edge functions are evaluated in a
+    // coordinate system that only depends on the ray, so triangles sharing an edge agree on
+    // which side of it a ray passes and no ray slips through between them. Returns t and the
+    // barycentric coordinates u, v (matching point_to_uv).
+    pub fn get_intersection(&self, ray: Ray, t_min: f64, t_max: f64) -> Option<(f64, f64, f64)> {
+        let coordinate = |p: Vec3D, k: usize| [p.x, p.y, p.z][k];
+        let direction = ray.direction;
+        // the ray runs along z in the new coordinate system, swapping x and y keeps the
+        // winding of the triangle when it runs along -z
+        let kz = if direction.x.abs() > direction.y.abs() && direction.x.abs() > direction.z.abs() {
+            0
+        } else if direction.y.abs() > direction.z.abs() {
+            1
         } else {
-            let t: f64 = (distance - ray.origin * normal) / (ray.direction * normal);
-            if t_min < t && t < t_max {
-                let p = ray.at(t);
+            2
+        };
+        let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+        if coordinate(direction, kz) < 0.0 {
+            std::mem::swap(&mut kx, &mut ky);
+        }
+        let dz = coordinate(direction, kz);
+        let shear_x = coordinate(direction, kx) / dz;
+        let shear_y = coordinate(direction, ky) / dz;
 
-                let test1: bool =
-                    Vec3D::cross(self.vert_b - self.vert_a, p - self.vert_a) * normal >= 0.0;
-                let test2: bool =
-                    Vec3D::cross(self.vert_c - self.vert_b, p - self.vert_b) * normal >= 0.0;
-                let test3: bool =
-                    Vec3D::cross(self.vert_a - self.vert_c, p - self.vert_c) * normal >= 0.0;
-                if test1 && test2 && test3 {
-                    Some(t)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
+        let corners = [self.vert_a, self.vert_b, self.vert_c].map(|vertex| {
+            let p = vertex - ray.origin;
+            let z = coordinate(p, kz);
+            (
+                coordinate(p, kx) - shear_x * z,
+                coordinate(p, ky) - shear_y * z,
+                z / dz,
+            )
+        });
+        let [(ax, ay, az), (bx, by, bz), (cx, cy, cz)] = corners;
+        // twice the signed areas of the triangles between the ray and each edge
+        let edge_a = cx * by - cy * bx;
+        let edge_b = ax * cy - ay * cx;
+        let edge_c = bx * ay - by * ax;
+        if (edge_a < 0.0 || edge_b < 0.0 || edge_c < 0.0)
+            && (edge_a > 0.0 || edge_b > 0.0 || edge_c > 0.0)
+        {
+            return None;
+        }
+        let determinant = edge_a + edge_b + edge_c;
+        if determinant == 0.0 {
+            return None;
+        }
+        let t = (edge_a * az + edge_b * bz + edge_c * cz) / determinant;
+        if t_min < t && t < t_max {
+            Some((t, edge_a / determinant, edge_b / determinant))
+        } else {
+            None
         }
     }
 
@@ -67,7 +130,7 @@ impl Triangle {
     pub fn min_z(&self) -> f64 {
         [self.vert_a.z, self.vert_b.z, self.vert_c.z]
             .into_iter()
-            .reduce(|a, b| f64::min(a, b))
+            .reduce(f64::min)
             .unwrap()
     }
 }
@@ -1,23 +1,40 @@
-use crate::camera::Camera;
-use crate::light::LightSource;
+use crate::camera::{cubemap_direction, Camera, FisheyeMapping, Projection, StereoLayout};
+use crate::light::{Light, LightSample};
+use crate::materials::{Hair, PhongModel, Subsurface};
+use crate::math::sampling::{cosine_hemisphere, power_heuristic, uniform_sphere};
 use crate::math::vector::Vec3D;
+use crate::media::{Medium, Volume};
 use crate::objects::{hittables::*, ray::Ray};
+use crate::sky::PhysicalSky;
 use crate::utils;
 
 use palette::{Clamp, ComponentWise, LinSrgb, Pixel, Srgb};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 use rayon::prelude::*;
 use std::sync::Arc;
 
 pub struct Scene {
     pub objects: Vec<Arc<dyn Hittable + Send + Sync>>,
-    pub light_sources: Vec<LightSource>,
+    pub light_sources: Vec<Arc<dyn Light + Send + Sync>>,
     pub max_depth: u32,
+    pub sky: Option<PhysicalSky>,
+    pub area_light_samples: u32, // shadow rays per emissive object and shading point
+    pub volumes: Vec<Volume>,
 }
 
 impl Scene {
-    pub fn get_first_intersection(&self, ray: Ray) -> Option<IntersectionData> {
+    pub fn get_first_intersection(&self, ray: Ray) -> Option<IntersectionData<'_>> {
+        self.get_first_hit(ray)
+            .map(|(_, intersection_data)| intersection_data)
+    }
+
+    // Like get_first_intersection, but also returns the object that was hit
+    fn get_first_hit(
+        &self,
+        ray: Ray,
+    ) -> Option<(&(dyn Hittable + Send + Sync), IntersectionData<'_>)> {
         let mut best = f64::INFINITY;
-        let mut winner: Option<IntersectionData> = None;
+        let mut winner = None;
         for object in &self.objects {
             match object.intersect(ray, 0.00001, best) {
                 Some(intersection_data) => {
@@ -26,7 +43,7 @@ impl Scene {
                     } else {
                         best
                     };
-                    winner = Some(intersection_data);
+                    winner = Some((object.as_ref(), intersection_data));
                 }
                 None => continue,
             }
@@ -34,88 +51,528 @@ impl Scene {
         winner
     }
 
-    pub fn is_free_path(&self, ray: Ray, t_min: f64, t_max: f64) -> bool {
+    // Fraction of light that passes along the ray between t_min and t_max. Opaque objects
+    // block the light completely, transmissive objects filter it every time it enters them
+    // and absorb it on the way to the exit, volumes attenuate it along the way.
+    pub fn transmittance(&self, ray: Ray, t_min: f64, t_max: f64, rng: &mut SmallRng) -> LinSrgb {
+        let mut transmittance = LinSrgb::new(1.0, 1.0, 1.0);
         for object in &self.objects {
-            match object.intersect(ray, t_min, t_max) {
-                Some(_) => return false,
-                None => continue,
+            let mut t_start = t_min;
+            let mut t_entry = t_min;
+            while let Some(intersection) = object.intersect(ray, t_start, t_max) {
+                match intersection.phong_data {
+                    Some(phong_model) if phong_model.is_transmissive() => {
+                        let filter = if intersection.front_face {
+                            t_entry = intersection.t;
+                            phong_model.get_transmission_at(intersection.u, intersection.v)
+                        } else {
+                            let distance = (intersection.t - t_entry) * ray.direction.norm();
+                            phong_model.get_absorption_over(distance)
+                        };
+                        transmittance = transmittance.component_wise(&filter, |a, b| a * b);
+                    }
+                    _ => return LinSrgb::new(0.0, 0.0, 0.0),
+                }
+                if transmittance == LinSrgb::new(0.0, 0.0, 0.0) {
+                    return transmittance;
+                }
+                t_start = intersection.t + 0.00001;
             }
         }
-        return true;
+        for volume in &self.volumes {
+            transmittance *= volume.transmittance(ray, t_min, t_max, rng) as f32;
+        }
+        transmittance
     }
 
-    fn get_ambient_light(&self) -> LinSrgb {
+    fn get_ambient_light(&self, time: f64) -> LinSrgb {
         let mut sum = LinSrgb::new(0.0, 0.0, 0.0);
         for light in &self.light_sources {
-            sum += light.color;
+            sum += light.color(time);
         }
         sum
     }
 
-    pub fn trace(&self, ray: Ray, depth: u32) -> LinSrgb {
-        let ambient_light = self.get_ambient_light();
-        match self.get_first_intersection(ray) {
-            Some(intersection) => {
-                let normal = intersection.normal;
-                let to_viewer = -ray.direction.unit_vector();
-                let intersection_point = ray.at(intersection.t);
-                let u = intersection.u;
-                let v = intersection.v;
-                let mut phong_color = LinSrgb::new(0.0, 0.0, 0.0); //phong_model.color;
-                let phong_model = intersection.phong_data.unwrap();
-                let object_color = phong_model.material.get_color_at(u, v);
-                let mut shadow: bool = true;
-                for light in &self.light_sources {
-                    let point_to_light = light.position - intersection_point;
-                    let dist = point_to_light.norm();
-                    let to_light = Ray {
-                        origin: intersection_point + 0.0001 * normal,
-                        direction: point_to_light / dist,
-                    };
-                    let dot_diffuse = (normal * to_light.direction) as f32;
-                    if dot_diffuse > 0.0 && self.is_free_path(to_light, 0.0, dist) {
-                        shadow = false;
-                        let diffuse_component = dot_diffuse;
-                        let dot_specular = (-to_light.direction).reflect(normal) * to_viewer;
-                        let specular_component = if dot_specular > 0.0 {
-                            dot_specular.powf(phong_model.alpha as f64) as f32
-                        } else {
-                            0.0
-                        };
+    fn get_background(&self, ray: Ray) -> LinSrgb {
+        match &self.sky {
+            Some(sky) => sky.radiance(ray.direction).clamp(),
+            None => LinSrgb::new(0.0, 0.0, 0.0),
+        }
+    }
 
-                        //println!("{:?}", to_light.direction.reflect(normal) * ray_from);
-                        // Add specular component to Phong Model blended color
-                        phong_color = phong_color.component_wise(&light.color, |a, b| {
-                            a + b * specular_component * phong_model.k_s
-                        });
-                        // Add diffuse component to Phong Model blended color
-                        phong_color = phong_color.component_wise(&object_color, |a, b| {
-                            a + b * diffuse_component * phong_model.k_d
-                        });
-                    }
-                }
-                let mut reflected_color = LinSrgb::new(0.0, 0.0, 0.0);
-                if depth < self.max_depth {
-                    let reflected_ray = Ray {
-                        origin: intersection_point + 0.0001 * normal,
-                        direction: intersection.ray.direction.reflect(normal),
-                    };
-                    reflected_color = self.trace(reflected_ray, depth + 1);
+    // Direct light from emissive objects, found by sampling points on their surfaces. Every
+    // sample is weighted against BSDF sampling (see sample_bsdf_emission) using multiple
+    // importance sampling with the power heuristic. bsdf_pdf gives the density with which
+    // the BSDF samples a direction, or zero where it isn't sampled. The object being shaded,
+    // identified by its material, doesn't light itself.
+    fn sample_area_lights(
+        &self,
+        point: Vec3D,
+        shaded: Option<&PhongModel>,
+        bsdf_pdf: &dyn Fn(Vec3D) -> f64,
+        rng: &mut SmallRng,
+    ) -> Vec<LightSample> {
+        let n_samples = self.area_light_samples;
+        let mut light_samples = Vec::new();
+        for object in &self.objects {
+            let emitter = match object.get_phong_data() {
+                Some(emitter) if emitter.is_emissive() => emitter,
+                _ => continue,
+            };
+            if shaded.is_some_and(|shaded| std::ptr::eq(shaded, emitter)) {
+                continue;
+            }
+            for _ in 0..n_samples {
+                let surface = match object.sample_surface(rng, point) {
+                    Some(surface) => surface,
+                    None => break,
+                };
+                let to_light = surface.point - point;
+                let distance = to_light.norm();
+                let direction = to_light / distance;
+                let cos_light = (surface.normal * direction).abs();
+                if distance <= f64::EPSILON || cos_light <= f64::EPSILON {
+                    continue;
                 }
-                let ambient_color = object_color
-                    .component_wise(&ambient_light, |a, b| 0.05 * phong_model.k_a * (a + b));
+                // convert the area density of the sample into a solid angle density
+                let pdf_light = surface.pdf * distance * distance / cos_light;
+                let pdf_bsdf = bsdf_pdf(direction);
+                let weight = power_heuristic(n_samples, pdf_light, n_samples, pdf_bsdf)
+                    / (n_samples as f64 * pdf_light);
+                light_samples.push(LightSample {
+                    direction,
+                    // stop shadow rays just short of the emitter itself
+                    distance: distance * (1.0 - 1e-4),
+                    radiance: emitter.get_emission_at(surface.u, surface.v) * weight as f32,
+                });
+            }
+        }
+        light_samples
+    }
 
-                reflected_color = if shadow {
-                    reflected_color.component_wise_self(|a| phong_model.k_s * phong_model.k_a * a)
-                } else {
-                    reflected_color.component_wise_self(|a| phong_model.k_s * a)
+    // Direct light from emissive objects, found by sampling directions from the BSDF and
+    // weighted against light sampling (see sample_area_lights)
+    #[allow(clippy::too_many_arguments)]
+    fn sample_bsdf_emission(
+        &self,
+        point: Vec3D,
+        time: f64,
+        normal: Vec3D,
+        to_viewer: Vec3D,
+        phong_model: &PhongModel,
+        object_color: LinSrgb,
+        rng: &mut SmallRng,
+    ) -> LinSrgb {
+        let n_samples = self.area_light_samples;
+        let mut color = LinSrgb::new(0.0, 0.0, 0.0);
+        for _ in 0..n_samples {
+            let direction = match phong_model.sample_direction(rng, normal, to_viewer) {
+                Some(direction) => direction,
+                None => break,
+            };
+            let pdf_bsdf = phong_model.pdf(normal, direction, to_viewer);
+            if direction * normal <= 0.0 || pdf_bsdf <= 0.0 {
+                continue;
+            }
+            let ray = Ray {
+                origin: point + 0.0001 * normal,
+                direction,
+                time,
+            };
+            let (object, hit) = match self.get_first_hit(ray) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            let emitter = match hit.phong_data {
+                Some(emitter) if emitter.is_emissive() => emitter,
+                _ => continue,
+            };
+            let cos_light = (hit.normal.unit_vector() * direction).abs();
+            // the shaded object itself isn't sampled as a light
+            let pdf_light = if std::ptr::eq(phong_model, emitter) {
+                0.0
+            } else if cos_light > f64::EPSILON {
+                object.sample_pdf(point, ray.at(hit.t)) * hit.t * hit.t / cos_light
+            } else {
+                0.0
+            };
+            let weight = power_heuristic(n_samples, pdf_bsdf, n_samples, pdf_light)
+                / (n_samples as f64 * pdf_bsdf);
+            let radiance = emitter.get_emission_at(hit.u, hit.v) * weight as f32;
+            let scattered = phong_model.eval(object_color, normal, direction, to_viewer);
+            color += scattered.component_wise(&radiance, |a, b| a * b);
+        }
+        color
+    }
+
+    // Light scattered towards the ray's origin at a collision with a medium
+    fn shade_volume(
+        &self,
+        medium: &Medium,
+        ray: Ray,
+        point: Vec3D,
+        depth: u32,
+        rng: &mut SmallRng,
+    ) -> LinSrgb {
+        use std::f64::consts::PI;
+        let mut color = LinSrgb::new(0.0, 0.0, 0.0);
+        let area_light_samples = self.sample_area_lights(point, None, &|_| 0.0, rng);
+        let light_samples: Vec<LightSample> = self
+            .light_sources
+            .iter()
+            .filter_map(|light| light.sample(point, ray.time))
+            .chain(area_light_samples)
+            .collect();
+        for light_sample in light_samples {
+            let to_light = Ray {
+                origin: point,
+                direction: light_sample.direction,
+                time: ray.time,
+            };
+            let transmittance = self.transmittance(to_light, 0.00001, light_sample.distance, rng);
+            // lights are scaled like in the Phong model, which leaves out the 1/pi of a
+            // diffuse BRDF, so the phase function is scaled up accordingly
+            let phase = PI * medium.phase(ray.direction * light_sample.direction);
+            let radiance = light_sample
+                .radiance
+                .component_wise(&transmittance, |a, b| a * b);
+            color += radiance * phase as f32;
+        }
+        if depth < self.max_depth {
+            let scattered_ray = Ray {
+                origin: point,
+                direction: medium.sample_direction(rng, ray.direction),
+                time: ray.time,
+            };
+            color += self.trace_ray(scattered_ray, depth + 1, true, rng);
+        }
+        let albedo = medium.albedo() as f32;
+        color
+            .component_wise(&medium.color, |a, b| a * b * albedo)
+            .clamp()
+    }
+
+    pub fn trace(&self, ray: Ray, depth: u32, rng: &mut SmallRng) -> LinSrgb {
+        self.trace_ray(ray, depth, false, rng)
+    }
+
+    // light_sampled tells if the ray was scattered where area lights were sampled at full
+    // weight, then emitters it hits were already accounted for
+    fn trace_ray(&self, ray: Ray, depth: u32, light_sampled: bool, rng: &mut SmallRng) -> LinSrgb {
+        // distances in volumes are measured along unit length rays
+        let ray = Ray {
+            direction: ray.direction.unit_vector(),
+            ..ray
+        };
+        let hit = self.get_first_hit(ray);
+        let t_surface = hit
+            .as_ref()
+            .map_or(f64::INFINITY, |(_, intersection)| intersection.t);
+
+        // delta tracking through all volumes, the nearest real collision scatters the ray
+        let mut collision: Option<(&Medium, f64)> = None;
+        for volume in &self.volumes {
+            let t_max = collision.map_or(t_surface, |(_, t)| t);
+            if let Some(t) = volume.sample_collision(ray, 0.0, t_max, rng) {
+                collision = Some((&volume.medium, t));
+            }
+        }
+        if let Some((medium, t)) = collision {
+            return self.shade_volume(medium, ray, ray.at(t), depth, rng);
+        }
+
+        match hit {
+            Some((object, intersection)) => {
+                self.shade_surface(object, intersection, depth, light_sampled, rng)
+            }
+            None => self.get_background(ray),
+        }
+    }
+
+    // Light leaving the surface at point after a random walk through the object below it.
+    // Only the object itself is searched for the exit, anything inside of it is ignored.
+    fn trace_subsurface(
+        &self,
+        object: &dyn Hittable,
+        subsurface: &Subsurface,
+        point: Vec3D,
+        normal: Vec3D,
+        time: f64,
+        rng: &mut SmallRng,
+    ) -> LinSrgb {
+        let mut throughput = LinSrgb::new(1.0, 1.0, 1.0);
+        let mut ray = Ray {
+            origin: point - 0.0001 * normal,
+            direction: cosine_hemisphere(rng, -normal),
+            time,
+        };
+        for _ in 0..subsurface.max_steps {
+            let exit = match object.intersect(ray, 0.00001, f64::INFINITY) {
+                Some(exit) => exit,
+                None => return LinSrgb::new(0.0, 0.0, 0.0), // the object isn't closed
+            };
+            let distance = -f64::ln(1.0 - rng.gen::<f64>()) * subsurface.mean_free_path;
+            if distance < exit.t {
+                // scatter inside of the object
+                throughput = throughput.component_wise(&subsurface.albedo, |a, b| a * b);
+                ray = Ray {
+                    origin: ray.at(distance),
+                    direction: uniform_sphere(rng),
+                    time,
+                };
+                continue;
+            }
+            // leave the object, the exit point is lit like a diffuse surface
+            let exit_point = ray.at(exit.t);
+            let outward = -exit.normal.unit_vector();
+            let mut radiance = LinSrgb::new(0.0, 0.0, 0.0);
+            let area_light_samples =
+                self.sample_area_lights(exit_point, object.get_phong_data(), &|_| 0.0, rng);
+            let light_samples: Vec<LightSample> = self
+                .light_sources
+                .iter()
+                .filter_map(|light| light.sample(exit_point, time))
+                .chain(area_light_samples)
+                .collect();
+            for light_sample in light_samples {
+                let cos_theta = outward * light_sample.direction;
+                if cos_theta <= 0.0 {
+                    continue;
+                }
+                let to_light = Ray {
+                    origin: exit_point + 0.0001 * outward,
+                    direction: light_sample.direction,
+                    time,
                 };
+                let transmittance = self.transmittance(to_light, 0.0, light_sample.distance, rng);
+                radiance += light_sample
+                    .radiance
+                    .component_wise(&transmittance, |a, b| a * b)
+                    * cos_theta as f32;
+            }
+            return radiance.component_wise(&throughput, |a, b| a * b);
+        }
+        LinSrgb::new(0.0, 0.0, 0.0)
+    }
 
-                let final_color = phong_color + reflected_color + ambient_color;
-                return final_color.clamp();
+    // Light scattered towards the ray's origin by a hair fiber running along tangent. Fibers
+    // are lit from all sides, light passing through them included, and gather indirect light
+    // from a uniformly sampled direction.
+    fn shade_hair(
+        &self,
+        hair: &Hair,
+        intersection: &IntersectionData,
+        tangent: Vec3D,
+        depth: u32,
+        light_sampled: bool,
+        rng: &mut SmallRng,
+    ) -> LinSrgb {
+        let ray = intersection.ray;
+        let normal = intersection.normal.unit_vector();
+        let to_viewer = -ray.direction.unit_vector();
+        let point = ray.at(intersection.t);
+        let (u, v) = (intersection.u, intersection.v);
+        let phong_model = intersection.phong_data.unwrap();
+        let fiber_color = phong_model.material.get_color_at(u, v);
+        // starts secondary rays on the side of the fiber they leave towards
+        let offset = |direction: Vec3D| {
+            if direction * normal < 0.0 {
+                point - 0.0001 * normal
+            } else {
+                point + 0.0001 * normal
             }
-            None => LinSrgb::new(0.0, 0.0, 0.0), //LinSrgb::new(117.0 / 255.0, 186.0 / 255.0, 1.0),
+        };
+
+        let mut color = LinSrgb::new(0.0, 0.0, 0.0);
+        let area_light_samples = self.sample_area_lights(point, Some(phong_model), &|_| 0.0, rng);
+        let light_samples: Vec<LightSample> = self
+            .light_sources
+            .iter()
+            .filter_map(|light| light.sample(point, ray.time))
+            .chain(area_light_samples)
+            .collect();
+        for light_sample in light_samples {
+            let to_light = Ray {
+                origin: offset(light_sample.direction),
+                direction: light_sample.direction,
+                time: ray.time,
+            };
+            let transmittance = self.transmittance(to_light, 0.0, light_sample.distance, rng);
+            let scattered = hair.eval(fiber_color, tangent, to_light.direction, to_viewer);
+            let radiance = light_sample
+                .radiance
+                .component_wise(&transmittance, |a, b| a * b);
+            color += scattered.component_wise(&radiance, |a, b| a * b);
+        }
+        if depth < self.max_depth {
+            let direction = uniform_sphere(rng);
+            let indirect_ray = Ray {
+                origin: offset(direction),
+                direction,
+                time: ray.time,
+            };
+            // eval is scaled up by pi, the pdf of uniform directions is 1 / (4 pi)
+            let scattered = hair.eval(fiber_color, tangent, direction, to_viewer) * 4.0;
+            let indirect = self.trace_ray(indirect_ray, depth + 1, true, rng);
+            color += scattered.component_wise(&indirect, |a, b| a * b);
         }
+        let ambient_color = fiber_color
+            .component_wise(&self.get_ambient_light(ray.time), |a, b| {
+                0.05 * phong_model.k_a * (a + b)
+            });
+        let emitted_color = if !light_sampled || self.area_light_samples == 0 {
+            phong_model.get_emission_at(u, v)
+        } else {
+            LinSrgb::new(0.0, 0.0, 0.0)
+        };
+        (color + ambient_color + emitted_color).clamp()
+    }
+
+    // Light reflected, transmitted and emitted towards the ray's origin at a surface
+    fn shade_surface(
+        &self,
+        object: &dyn Hittable,
+        intersection: IntersectionData,
+        depth: u32,
+        light_sampled: bool,
+        rng: &mut SmallRng,
+    ) -> LinSrgb {
+        let ambient_light = self.get_ambient_light(intersection.ray.time);
+        let ray = intersection.ray;
+        let normal = intersection.normal.unit_vector();
+        let to_viewer = -ray.direction.unit_vector();
+        let intersection_point = ray.at(intersection.t);
+        let u = intersection.u;
+        let v = intersection.v;
+        let mut phong_color = LinSrgb::new(0.0, 0.0, 0.0);
+        let phong_model = intersection.phong_data.unwrap();
+        if let (Some(hair), Some(tangent)) = (&phong_model.hair, intersection.tangent) {
+            return self.shade_hair(hair, &intersection, tangent, depth, light_sampled, rng);
+        }
+        let object_color = phong_model.material.get_color_at(u, v);
+        // subsurface scattering replaces the diffuse component
+        let diffuse_color = match phong_model.subsurface {
+            Some(_) => LinSrgb::new(0.0, 0.0, 0.0),
+            None => object_color,
+        };
+        let mut shadow: bool = true;
+        let area_light_samples = self.sample_area_lights(
+            intersection_point,
+            Some(phong_model),
+            &|direction| phong_model.pdf(normal, direction, to_viewer),
+            rng,
+        );
+        let light_samples = self
+            .light_sources
+            .iter()
+            .filter_map(|light| light.sample(intersection_point, ray.time))
+            .chain(area_light_samples);
+        for light_sample in light_samples {
+            let to_light = Ray {
+                origin: intersection_point + 0.0001 * normal,
+                direction: light_sample.direction,
+                time: ray.time,
+            };
+            if normal * to_light.direction <= 0.0 {
+                continue;
+            }
+            let transmittance = self.transmittance(to_light, 0.0, light_sample.distance, rng);
+            if transmittance != LinSrgb::new(0.0, 0.0, 0.0) {
+                shadow = false;
+                // Add diffuse and specular components to Phong Model blended color
+                let scattered =
+                    phong_model.eval(diffuse_color, normal, to_light.direction, to_viewer);
+                let radiance = light_sample
+                    .radiance
+                    .component_wise(&transmittance, |a, b| a * b);
+                phong_color += scattered.component_wise(&radiance, |a, b| a * b);
+            }
+        }
+        phong_color += self.sample_bsdf_emission(
+            intersection_point,
+            ray.time,
+            normal,
+            to_viewer,
+            phong_model,
+            diffuse_color,
+            rng,
+        );
+        if let Some(subsurface) = &phong_model.subsurface {
+            if intersection.front_face {
+                phong_color += self.trace_subsurface(
+                    object,
+                    subsurface,
+                    intersection_point,
+                    normal,
+                    ray.time,
+                    rng,
+                ) * phong_model.k_d;
+            }
+        }
+        let mut reflected_color = LinSrgb::new(0.0, 0.0, 0.0);
+        if depth < self.max_depth {
+            let reflected_ray = Ray {
+                origin: intersection_point + 0.0001 * normal,
+                direction: intersection.ray.direction.reflect(normal),
+                time: ray.time,
+            };
+            reflected_color = self.trace_ray(reflected_ray, depth + 1, false, rng);
+        }
+        let mut refracted_color = LinSrgb::new(0.0, 0.0, 0.0);
+        if phong_model.is_transmissive() && depth < self.max_depth {
+            let eta = if intersection.front_face {
+                1.0 / phong_model.ior
+            } else {
+                phong_model.ior
+            };
+            // total internal reflection sends the light back into the object
+            let direction = (-to_viewer)
+                .refract(normal, eta)
+                .unwrap_or_else(|| (-to_viewer).reflect(normal));
+            let offset = if direction * normal < 0.0 {
+                -normal
+            } else {
+                normal
+            };
+            let refracted_ray = Ray {
+                origin: intersection_point + 0.0001 * offset,
+                direction,
+                time: ray.time,
+            };
+            refracted_color = self.trace_ray(refracted_ray, depth + 1, false, rng);
+            // light is filtered when it enters the object
+            let filter = if intersection.front_face {
+                phong_model.get_transmission_at(u, v)
+            } else {
+                LinSrgb::new(1.0, 1.0, 1.0)
+            };
+            refracted_color = refracted_color.component_wise(&filter, |a, b| a * b);
+        }
+        let ambient_color =
+            object_color.component_wise(&ambient_light, |a, b| 0.05 * phong_model.k_a * (a + b));
+
+        reflected_color = if shadow {
+            reflected_color.component_wise_self(|a| phong_model.k_s * phong_model.k_a * a)
+        } else {
+            reflected_color.component_wise_self(|a| phong_model.k_s * a)
+        };
+
+        // Emitters hit after a bounce that sampled area lights were already accounted for by
+        // its direct lighting, mirror reflections and refractions don't sample them
+        let emitted_color = if !light_sampled || self.area_light_samples == 0 {
+            phong_model.get_emission_at(u, v)
+        } else {
+            LinSrgb::new(0.0, 0.0, 0.0)
+        };
+
+        let mut final_color =
+            phong_color + reflected_color + refracted_color + ambient_color + emitted_color;
+        // a ray hitting a surface from the inside has traveled through the object since entering
+        if !intersection.front_face {
+            let absorption = phong_model.get_absorption_over(intersection.t * ray.direction.norm());
+            final_color = final_color.component_wise(&absorption, |a, b| a * b);
+        }
+        final_color.clamp()
     }
 }
 
@@ -125,33 +582,146 @@ pub struct Renderer {
     pub width: u32,
     pub height: u32,
     pub h_fov: f64,
+    pub samples_per_pixel: u32, // more than one sample jitters the rays within each pixel
 }
 
 impl Renderer {
-    fn render_row(&self, row: u32) -> Vec<u8> {
+    // Origin and direction of the ray through a point on the image, given in pixels. Returns
+    // None where the projection doesn't cover the image, like the corners of a fisheye image.
+    fn primary_ray(&self, x: f64, y: f64) -> Option<(Vec3D, Vec3D)> {
+        use std::f64::consts::PI;
+        // each eye of a stereo camera sees half of the image
+        let (mut x, mut y, mut width, mut height) = (x, y, self.width, self.height);
+        let mut eye = 0.0; // -1 for the left eye, 1 for the right one
+        if let Some(stereo) = self.camera.stereo {
+            let (position, size) = match stereo.layout {
+                StereoLayout::SideBySide => (&mut x, &mut width),
+                StereoLayout::TopBottom => (&mut y, &mut height),
+            };
+            // the pixel's center decides which eye it belongs to, with an odd size the right
+            // eye gets the extra pixel
+            let left = *size / 2;
+            if (*position + 0.5).floor() >= left as f64 {
+                *position -= left as f64;
+                *size -= left;
+                eye = 1.0;
+            } else {
+                *size = left;
+                eye = -1.0;
+            }
+        }
         let cam_dir = (self.camera.look_at - self.camera.origin).unit_vector();
         let y_dir = Vec3D::new(0.0, 1.0, 0.0);
         let cam_right = Vec3D::cross(cam_dir, y_dir).unit_vector();
         let cam_up = Vec3D::cross(cam_right, cam_dir);
-        let inv_ar = self.get_inv_aspect_ratio();
-        let gx = f64::tan(self.h_fov / 2.0);
-        let gy = gx * inv_ar;
-        let x_shift: Vec3D = ((2.0 * gx) / (self.width - 1) as f64) * cam_right;
-        let y_shift: Vec3D = ((2.0 * gy) / (self.height - 1) as f64) * cam_up;
-        let left_side: Vec3D = cam_dir - gx * cam_right + gy * cam_up - (row as f64) * y_shift;
+        // from -1 to 1 across the image, up is positive
+        let ndc_x = 2.0 * x / (width - 1) as f64 - 1.0;
+        let ndc_y = 1.0 - 2.0 * y / (height - 1) as f64;
+        // from 0 to 1 between the outer edges of the image, for projections that wrap around
+        let (wrap_x, wrap_y) = ((x + 0.5) / width as f64, (y + 0.5) / height as f64);
+        let inv_ar = (height - 1) as f64 / (width - 1) as f64;
+        let mut origin = self.camera.origin;
+        let direction = match self.camera.projection {
+            Projection::Perspective => {
+                let gx = f64::tan(self.h_fov / 2.0);
+                let gy = gx * inv_ar;
+                cam_dir + (ndc_x * gx) * cam_right + (ndc_y * gy) * cam_up
+            }
+            Projection::Orthographic { view_width } => {
+                let gx = view_width / 2.0;
+                let gy = gx * inv_ar;
+                origin += (ndc_x * gx) * cam_right + (ndc_y * gy) * cam_up;
+                cam_dir
+            }
+            Projection::Equirectangular => {
+                let longitude = (2.0 * wrap_x - 1.0) * PI;
+                let latitude = (0.5 - wrap_y) * PI;
+                latitude.cos() * (longitude.sin() * cam_right + longitude.cos() * cam_dir)
+                    + latitude.sin() * cam_up
+            }
+            Projection::Cubemap => {
+                let face_x = (6.0 * wrap_x).min(5.999);
+                let face = face_x as usize;
+                let s = 2.0 * (face_x - face as f64) - 1.0;
+                cubemap_direction(face, s, 2.0 * wrap_y - 1.0)
+            }
+            Projection::Fisheye(mapping) => {
+                let (image_x, image_y) = (ndc_x, ndc_y * inv_ar);
+                let r = f64::hypot(image_x, image_y);
+                let half_fov = self.h_fov / 2.0;
+                let angle = match mapping {
+                    FisheyeMapping::Equidistant => r * half_fov,
+                    FisheyeMapping::Equisolid => {
+                        // r = sin(angle / 2), scaled to 1 at half the field of view
+                        let sin_half_angle = r * f64::sin(half_fov / 2.0);
+                        if sin_half_angle > 1.0 {
+                            return None;
+                        }
+                        2.0 * sin_half_angle.asin()
+                    }
+                };
+                if angle > PI {
+                    return None;
+                }
+                let (cos_phi, sin_phi) = if r > 0.0 {
+                    (image_x / r, image_y / r)
+                } else {
+                    (1.0, 0.0)
+                };
+                angle.cos() * cam_dir + angle.sin() * (cos_phi * cam_right + sin_phi * cam_up)
+            }
+        };
+        let stereo = match self.camera.stereo {
+            Some(stereo) => stereo,
+            None => return Some((origin, direction)),
+        };
+        let sideways = match self.camera.projection {
+            Projection::Equirectangular => direction.cross(cam_up),
+            Projection::Cubemap => direction.cross(y_dir),
+            _ => cam_right,
+        };
+        if sideways.almost_zero() {
+            // straight up or down in a panorama both eyes see the same
+            return Some((origin, direction));
+        }
+        let eye_origin =
+            origin + (eye * stereo.interocular_distance / 2.0) * sideways.unit_vector();
+        if stereo.convergence_distance.is_infinite() {
+            return Some((eye_origin, direction));
+        }
+        let target = origin + stereo.convergence_distance * direction;
+        Some((eye_origin, target - eye_origin))
+    }
+
+    fn render_row(&self, row: u32) -> Vec<u8> {
+        let mut rng = SmallRng::seed_from_u64(row as u64);
+        let n_samples = self.samples_per_pixel.max(1);
 
         (0..self.width)
-            .into_iter()
-            .map(|x| {
-                let ray = Ray {
-                    origin: self.camera.origin,
-                    direction: left_side + (x as f64) * x_shift,
-                };
-                Srgb::from_linear(self.scene.trace(ray, 0))
+            .flat_map(|x| {
+                let mut color = LinSrgb::new(0.0, 0.0, 0.0);
+                for _ in 0..n_samples {
+                    let (dx, dy) = if n_samples > 1 {
+                        (rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5)
+                    } else {
+                        (0.0, 0.0)
+                    };
+                    // pixels outside of the projection stay black
+                    let (x, y) = (x as f64 + dx, row as f64 + dy);
+                    let Some((origin, direction)) = self.primary_ray(x, y) else {
+                        continue;
+                    };
+                    let ray = Ray {
+                        origin,
+                        direction,
+                        time: self.camera.sample_time(&mut rng),
+                    };
+                    color += self.scene.trace(ray, 0, &mut rng);
+                }
+                Srgb::from_linear(color / n_samples as f32)
                     .into_format()
                     .into_raw::<[u8; 3]>() //<---- convert into byte array
             })
-            .flatten()
             .collect()
     }
 
@@ -166,10 +736,6 @@ impl Renderer {
             .collect()
     }
 
-    fn get_inv_aspect_ratio(&self) -> f64 {
-        ((self.height - 1) as f64) / ((self.width - 1) as f64)
-    }
-
     pub fn save_ppm(&self, filename: &str) -> Result<(), std::io::Error> {
         utils::save_ppm(filename, self.width, self.height, &self.render_scene())
     }
@@ -0,0 +1,222 @@
+use crate::light::DirectionalLight;
+use crate::math::vector::Vec3D;
+
+use palette::LinSrgb;
+use std::f64::consts::PI;
+
+// Position of the sun on the sky dome. The elevation is measured from the horizon,
+// the azimuth clockwise from north. North is the +z axis and east the +x axis, y points up.
+#[derive(Debug, Clone, Copy)]
+pub struct SunPosition {
+    pub elevation: f64,
+    pub azimuth: f64,
+}
+
+impl SunPosition {
+    // Angles are given in degrees
+    pub fn from_angles(elevation: f64, azimuth: f64) -> Self {
+        SunPosition {
+            elevation: elevation.to_radians(),
+            azimuth: azimuth.to_radians(),
+        }
+    }
+
+    // Approximate sun position for a day of the year (1..=365), local solar time in hours
+    // and latitude in degrees (positive on the northern hemisphere)
+    pub fn from_date_time(day_of_year: u32, solar_time: f64, latitude: f64) -> Self {
+        let latitude = latitude.to_radians();
+        let declination =
+            f64::to_radians(23.44) * f64::sin(2.0 * PI * (284.0 + day_of_year as f64) / 365.0);
+        let hour_angle = f64::to_radians(15.0 * (solar_time - 12.0));
+
+        let sin_elevation = latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos();
+        let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+
+        let cos_azimuth = (declination.sin() - sin_elevation * latitude.sin())
+            / (elevation.cos() * latitude.cos());
+        let mut azimuth = cos_azimuth.clamp(-1.0, 1.0).acos();
+        // afternoon sun is in the west
+        if hour_angle > 0.0 {
+            azimuth = 2.0 * PI - azimuth;
+        }
+        SunPosition { elevation, azimuth }
+    }
+
+    // Unit vector pointing from the scene towards the sun
+    pub fn direction(&self) -> Vec3D {
+        Vec3D::new(
+            self.elevation.cos() * self.azimuth.sin(),
+            self.elevation.sin(),
+            self.elevation.cos() * self.azimuth.cos(),
+        )
+    }
+}
+
+// Coefficients of the Perez sky luminance distribution
+#[derive(Debug, Clone, Copy)]
+struct Perez {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+}
+
+impl Perez {
+    // theta = zenith angle of the viewing direction, gamma = angle between viewing direction and sun
+    fn eval(&self, theta: f64, gamma: f64) -> f64 {
+        (1.0 + self.a * f64::exp(self.b / theta.cos()))
+            * (1.0 + self.c * f64::exp(self.d * gamma) + self.e * gamma.cos().powi(2))
+    }
+}
+
+// Analytic daylight sky after Preetham, Shirley and Smits, "A Practical Analytic Model for Daylight"
+#[derive(Debug, Clone)]
+pub struct PhysicalSky {
+    pub sun: SunPosition,
+    pub turbidity: f64,
+    // scales the sky luminance (given in kcd/m^2) into the renderer's color range
+    pub exposure: f32,
+    // scales the color of the matching sun light
+    pub sun_intensity: f32,
+    zenith: [f64; 3], // Yxy at zenith
+    perez: [Perez; 3],
+}
+
+impl PhysicalSky {
+    pub fn new(sun: SunPosition, turbidity: f64) -> Self {
+        let t = turbidity;
+        let theta_s = PI / 2.0 - sun.elevation.max(0.0);
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let (t2, th, th2, th3) = (t * t, theta_s, theta_s * theta_s, theta_s.powi(3));
+        let zenith_x = t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+        let zenith_y = t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+        let perez = [
+            Perez {
+                a: 0.1787 * t - 1.4630,
+                b: -0.3554 * t + 0.4275,
+                c: -0.0227 * t + 5.3251,
+                d: 0.1206 * t - 2.5771,
+                e: -0.0670 * t + 0.3703,
+            },
+            Perez {
+                a: -0.0193 * t - 0.2592,
+                b: -0.0665 * t + 0.0008,
+                c: -0.0004 * t + 0.2125,
+                d: -0.0641 * t - 0.8989,
+                e: -0.0033 * t + 0.0452,
+            },
+            Perez {
+                a: -0.0167 * t - 0.2608,
+                b: -0.0950 * t + 0.0092,
+                c: -0.0079 * t + 0.2102,
+                d: -0.0441 * t - 1.6537,
+                e: -0.0109 * t + 0.0529,
+            },
+        ];
+
+        PhysicalSky {
+            sun,
+            turbidity,
+            exposure: 0.05,
+            sun_intensity: 1.0,
+            zenith: [zenith_luminance.max(0.0), zenith_x, zenith_y],
+            perez,
+        }
+    }
+
+    pub fn from_elevation_azimuth(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        Self::new(SunPosition::from_angles(elevation, azimuth), turbidity)
+    }
+
+    pub fn from_date_time(
+        day_of_year: u32,
+        solar_time: f64,
+        latitude: f64,
+        turbidity: f64,
+    ) -> Self {
+        Self::new(
+            SunPosition::from_date_time(day_of_year, solar_time, latitude),
+            turbidity,
+        )
+    }
+
+    pub fn sun_direction(&self) -> Vec3D {
+        self.sun.direction()
+    }
+
+    // Sky radiance seen along direction. Directions below the horizon get the horizon's color.
+    pub fn radiance(&self, direction: Vec3D) -> LinSrgb {
+        let dir = direction.unit_vector();
+        let sun_dir = self.sun_direction();
+        let theta_s = PI / 2.0 - self.sun.elevation.max(0.0);
+        let theta = f64::acos(dir.y.max(0.001));
+        let gamma = f64::acos((dir * sun_dir).clamp(-1.0, 1.0));
+
+        let yxy: Vec<f64> = (0..3)
+            .map(|i| {
+                self.zenith[i] * self.perez[i].eval(theta, gamma) / self.perez[i].eval(0.0, theta_s)
+            })
+            .collect();
+        // fade out the sky once the sun sets
+        let twilight = ((self.sun.elevation + 0.1) / 0.1).clamp(0.0, 1.0);
+        yxy_to_linear_rgb(yxy[0] * twilight, yxy[1], yxy[2], self.exposure)
+    }
+
+    // Color of direct sunlight after extinction in the atmosphere, using Rayleigh scattering and
+    // the turbidity dependent aerosol extinction from the appendix of Preetham et al.
+    pub fn sun_color(&self) -> LinSrgb {
+        if self.sun.elevation <= 0.0 {
+            return LinSrgb::new(0.0, 0.0, 0.0);
+        }
+        let zenith_deg = 90.0 - self.sun.elevation.to_degrees();
+        // relative optical air mass (Kasten and Young)
+        let air_mass = 1.0
+            / (f64::cos(zenith_deg.to_radians()) + 0.50572 * (96.07995 - zenith_deg).powf(-1.6364));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |wavelength: f64| -> f32 {
+            let rayleigh = 0.008735 * wavelength.powf(-4.08);
+            let aerosol = beta * wavelength.powf(-1.3);
+            f64::exp(-air_mass * (rayleigh + aerosol)) as f32
+        };
+        LinSrgb::new(
+            transmittance(0.65),
+            transmittance(0.55),
+            transmittance(0.45),
+        )
+    }
+
+    // Directional light matching the sky's sun
+    pub fn sun_light(&self) -> DirectionalLight {
+        DirectionalLight {
+            direction: -self.sun_direction(),
+            color: self.sun_color(),
+            irradiance: self.sun_intensity,
+        }
+    }
+}
+
+fn yxy_to_linear_rgb(luminance: f64, x: f64, y: f64, exposure: f32) -> LinSrgb {
+    if y <= 0.0 {
+        return LinSrgb::new(0.0, 0.0, 0.0);
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    let big_y = luminance;
+    let r = 3.2404542 * big_x - 1.5371385 * big_y - 0.4985314 * big_z;
+    let g = -0.9692660 * big_x + 1.8760108 * big_y + 0.0415560 * big_z;
+    let b = 0.0556434 * big_x - 0.2040259 * big_y + 1.0572252 * big_z;
+    LinSrgb::new(
+        r.max(0.0) as f32 * exposure,
+        g.max(0.0) as f32 * exposure,
+        b.max(0.0) as f32 * exposure,
+    )
+}
@@ -0,0 +1,169 @@
+use simple_raytracer::animation::*;
+use simple_raytracer::camera::{Camera, Projection};
+use simple_raytracer::light::*;
+use simple_raytracer::materials::{Material, PhongModel};
+use simple_raytracer::math::vector::Vec3D;
+use simple_raytracer::objects::{hittables::Hittable, motion::*, ray::Ray, sphere::Sphere};
+use simple_raytracer::scene::{Renderer, Scene};
+
+use palette::LinSrgb;
+use std::sync::Arc;
+
+#[test]
+fn test_tracks() {
+    let keys: [(f64, f64); 4] = [(2.0, 1.0), (0.0, 0.0), (1.0, 3.0), (3.0, 0.0)];
+    let linear = Track::new(Interpolation::Linear, &keys);
+    assert_eq!(linear.keys[1].time, 1.0);
+    assert_eq!(linear.value_at(0.5), Some(1.5));
+    assert_eq!(linear.value_at(1.5), Some(2.0));
+    // values are held outside of the keys
+    assert_eq!(linear.value_at(-1.0), Some(0.0));
+    assert_eq!(linear.value_at(10.0), Some(0.0));
+    assert_eq!(
+        Track::<f64>::new(Interpolation::Linear, &[]).value_at(0.0),
+        None
+    );
+    assert_eq!(Track::constant(4.0).value_at(7.0), Some(4.0));
+
+    // the spline passes through the keys, with a slope of (1 - 0) / 2 at t = 1
+    let spline = Track::new(Interpolation::CatmullRom, &keys);
+    for (time, value) in keys {
+        assert!((spline.value_at(time).unwrap() - value).abs() < 1e-12);
+    }
+    let h = 1e-6;
+    let slope = (spline.value_at(1.0 + h).unwrap() - spline.value_at(1.0 - h).unwrap()) / (2.0 * h);
+    assert!((slope - 0.5).abs() < 1e-6);
+    // and overshoots the highest key, staying within the control points
+    let peak = (0..=300)
+        .map(|i| spline.value_at(i as f64 / 100.0).unwrap())
+        .fold(0.0, f64::max);
+    assert!(peak > 3.0);
+    assert!(spline.control_points().iter().any(|&p| p >= peak));
+
+    let path = Track::new(
+        Interpolation::CatmullRom,
+        &[
+            (0.0, Vec3D::new(1.0, 0.0, 0.0)),
+            (1.0, Vec3D::new(0.0, 1.0, 0.0)),
+        ],
+    );
+    assert!(path
+        .value_at(0.5)
+        .unwrap()
+        .almost_equal(Vec3D::new(0.5, 0.5, 0.0)));
+}
+
+#[test]
+fn test_animated_scene() {
+    let light = AnimatedLight {
+        light: PointLight {
+            position: Vec3D::new(0.0, 1.0, 0.0),
+            color: LinSrgb::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+        },
+        tracks: LightTracks {
+            position: Some(Track::new(
+                Interpolation::Linear,
+                &[
+                    (0.0, Vec3D::new(0.0, 1.0, 0.0)),
+                    (1.0, Vec3D::new(0.0, 2.0, 0.0)),
+                ],
+            )),
+            color: Some(Track::new(
+                Interpolation::Linear,
+                &[
+                    (0.0, LinSrgb::new(1.0, 1.0, 1.0)),
+                    (1.0, LinSrgb::new(1.0, 0.0, 0.0)),
+                ],
+            )),
+            ..Default::default()
+        },
+    };
+    assert_eq!(light.sample(Vec3D::default(), 0.0).unwrap().distance, 1.0);
+    let sample = light.sample(Vec3D::default(), 1.0).unwrap();
+    assert_eq!(sample.distance, 2.0);
+    assert_eq!((sample.radiance.red, sample.radiance.green), (0.25, 0.0));
+    assert_eq!(light.color(0.5).green, 0.5);
+
+    // objects follow their tracks at the time of the ray
+    let sphere = Moving {
+        object: Arc::new(Sphere {
+            center: Vec3D::default(),
+            radius: 1.0,
+            phong_data: Some(PhongModel {
+                material: Material::Color(LinSrgb::new(1.0, 1.0, 1.0)),
+                k_a: 1.0,
+                ..PhongModel::new()
+            }),
+        }),
+        motion: Motion::Keyframes {
+            translation: Track::new(
+                Interpolation::CatmullRom,
+                &[
+                    (0.0, Vec3D::new(-2.0, 0.0, 0.0)),
+                    (1.0, Vec3D::new(2.0, 0.0, 0.0)),
+                ],
+            ),
+            rotation: Track::new(Interpolation::Linear, &[]),
+        },
+    };
+    let bounds = sphere.bounding_box().unwrap();
+    assert!(bounds.min.x <= -3.0 && bounds.max.x >= 3.0 && bounds.max.y <= 1.0);
+    for (time, hit) in [(0.0, false), (0.5, true), (1.0, false)] {
+        let ray = Ray {
+            origin: Vec3D::new(0.0, 0.0, 5.0),
+            direction: Vec3D::new(0.0, 0.0, -1.0),
+            time,
+        };
+        assert_eq!(sphere.intersect(ray, 0.0, f64::INFINITY).is_some(), hit);
+    }
+
+    assert_eq!(frame_filename("turntable_###.ppm", 7), "turntable_007.ppm");
+    assert_eq!(frame_filename("frame#.ppm", 1234), "frame1234.ppm");
+
+    let mut renderer = Renderer {
+        scene: Scene {
+            objects: vec![Arc::new(sphere)],
+            light_sources: vec![Arc::new(light)],
+            max_depth: 1,
+            sky: None,
+            area_light_samples: 0,
+            volumes: vec![],
+        },
+        camera: Camera {
+            origin: Vec3D::new(0.0, 0.0, 5.0),
+            look_at: Vec3D::default(),
+            up: Vec3D::new(0.0, 1.0, 0.0),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            projection: Projection::Perspective,
+            stereo: None,
+        },
+        width: 4,
+        height: 3,
+        h_fov: f64::to_radians(10.0),
+        samples_per_pixel: 1,
+    };
+    let animation = CameraAnimation {
+        h_fov: Some(Track::new(
+            Interpolation::Linear,
+            &[(0.0, f64::to_radians(10.0)), (1.0, f64::to_radians(90.0))],
+        )),
+        ..Default::default()
+    };
+    let pattern = std::env::temp_dir().join("simple_raytracer_frame_##.ppm");
+    let pattern = pattern.to_str().unwrap();
+    render_frames(&mut renderer, &animation, 1..4, 2.0, pattern).unwrap();
+    // whether the pixel in the middle row and given column is lit
+    let lit = |frame: u32, column: usize| {
+        let ppm = std::fs::read(frame_filename(pattern, frame)).unwrap();
+        let pixel = ppm.len() - 3 * 4 * 3 + 3 * (4 + column);
+        ppm[pixel..pixel + 3].iter().any(|&b| b > 0)
+    };
+    // the sphere is centered in the first frame, then moves right while the view widens
+    assert!(!lit(1, 0) && lit(1, 1) && lit(1, 2));
+    assert!(!lit(3, 1) && lit(3, 2));
+    assert!(!std::path::Path::new(&frame_filename(pattern, 4)).exists());
+    // the camera is left as it was
+    assert_eq!(renderer.h_fov, f64::to_radians(10.0));
+}
@@ -0,0 +1,180 @@
+use simple_raytracer::camera::*;
+use simple_raytracer::materials::{Material, PhongModel};
+use simple_raytracer::math::vector::Vec3D;
+use simple_raytracer::objects::sphere::Sphere;
+use simple_raytracer::scene::{Renderer, Scene};
+
+use palette::LinSrgb;
+use std::sync::Arc;
+
+// Renderer looking down -z from z = 5 at glowing white spheres with the given centers
+fn glowing_spheres(spheres: &[Vec3D], radius: f64, width: u32, height: u32) -> Renderer {
+    Renderer {
+        scene: Scene {
+            objects: spheres
+                .iter()
+                .map(|&center| {
+                    Arc::new(Sphere {
+                        center,
+                        radius,
+                        phong_data: Some(PhongModel {
+                            emission: Material::Color(LinSrgb::new(1.0, 1.0, 1.0)),
+                            emission_strength: 1.0,
+                            ..PhongModel::new()
+                        }),
+                    }) as _
+                })
+                .collect(),
+            light_sources: vec![],
+            max_depth: 1,
+            sky: None,
+            area_light_samples: 0,
+            volumes: vec![],
+        },
+        camera: Camera {
+            origin: Vec3D::new(0.0, 0.0, 5.0),
+            look_at: Vec3D::default(),
+            up: Vec3D::new(0.0, 1.0, 0.0),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            projection: Projection::Perspective,
+            stereo: None,
+        },
+        width,
+        height,
+        h_fov: f64::to_radians(90.0),
+        samples_per_pixel: 1,
+    }
+}
+
+// Which pixels of the rendered image are lit, row by row
+fn lit_pixels(renderer: &Renderer) -> Vec<Vec<bool>> {
+    renderer
+        .render_scene()
+        .chunks(3 * renderer.width as usize)
+        .map(|row| {
+            row.chunks(3)
+                .map(|pixel| pixel.iter().any(|&b| b > 0))
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_orthographic_projection() {
+    // a near and a far sphere of the same size, off to the right and left
+    let spheres = [Vec3D::new(2.0, 0.0, 0.0), Vec3D::new(-2.0, 0.0, -50.0)];
+    let mut renderer = glowing_spheres(&spheres, 0.6, 7, 5);
+    let perspective = lit_pixels(&renderer);
+    // the far sphere is too small to be hit by any perspective ray
+    assert!(perspective[2]
+        .iter()
+        .enumerate()
+        .all(|(x, &lit)| lit == (x == 4)));
+
+    // with parallel rays one unit apart both spheres show up at their true size and position
+    renderer.camera.projection = Projection::Orthographic { view_width: 6.0 };
+    let orthographic = lit_pixels(&renderer);
+    assert_eq!(
+        orthographic[2],
+        [false, true, false, false, false, true, false]
+    );
+    // the view is 6 by 4 units, so rows are one unit apart as well
+    assert_eq!(orthographic[1], [false; 7]);
+}
+
+#[test]
+fn test_panoramic_projections() {
+    // the camera at z = 5 looks at a sphere in front of it and has one behind it
+    let mut renderer = glowing_spheres(&[Vec3D::default(), Vec3D::new(0.0, 0.0, 10.0)], 3.0, 8, 4);
+    renderer.camera.projection = Projection::Equirectangular;
+    let panorama = lit_pixels(&renderer);
+    // the view direction is in the center, the back wraps around the left and right edges
+    for row in [1, 2] {
+        assert_eq!(
+            panorama[row],
+            [true, false, false, true, true, false, false, true]
+        );
+    }
+    assert!(!panorama[0].contains(&true) && !panorama[3].contains(&true));
+
+    // a sphere filling the view along +x shows up on the first cube face only
+    let mut renderer = glowing_spheres(&[Vec3D::new(5.0, 0.0, 5.0)], 4.0, 12, 2);
+    renderer.camera.projection = Projection::Cubemap;
+    for row in lit_pixels(&renderer) {
+        assert_eq!(row[..2], [true, true]);
+        assert!(!row[2..].contains(&true));
+    }
+    let forward = cubemap_direction(5, 0.0, 0.0);
+    assert_eq!(forward, Vec3D::new(0.0, 0.0, -1.0));
+
+    // a full circle fisheye sees straight back all around its edge, the corners are outside
+    let mut renderer = glowing_spheres(&[Vec3D::new(0.0, 0.0, 10.0)], 1.0, 5, 5);
+    renderer.camera.projection = Projection::Fisheye(FisheyeMapping::Equidistant);
+    renderer.h_fov = 2.0 * std::f64::consts::PI;
+    let fisheye = lit_pixels(&renderer);
+    assert_eq!(fisheye[2], [true, false, false, false, true]);
+    assert_eq!(fisheye[0], [false, false, true, false, false]);
+    // half of that with equal solid angles reaches 90 degrees at the edges and 180 degrees in
+    // the corners
+    renderer.camera.projection = Projection::Fisheye(FisheyeMapping::Equisolid);
+    renderer.h_fov = std::f64::consts::PI;
+    let fisheye = lit_pixels(&renderer);
+    assert_eq!(fisheye[2], [false; 5]);
+    assert_eq!(fisheye[0], [true, false, false, false, true]);
+}
+
+#[test]
+fn test_stereo() {
+    // two 5x3 views side by side of a sphere in front of the camera
+    let mut renderer = glowing_spheres(&[Vec3D::default()], 0.6, 10, 3);
+    renderer.camera.stereo = Some(Stereo {
+        layout: StereoLayout::SideBySide,
+        interocular_distance: 4.0,
+        convergence_distance: f64::INFINITY,
+    });
+    // with parallel views each eye sees the sphere off to the side of the other eye
+    let [left, right] = [0, 1].map(|eye| lit_pixels(&renderer)[1][5 * eye..5 * eye + 5].to_vec());
+    assert_eq!(left, [false, false, false, true, false]);
+    assert_eq!(right, [false, true, false, false, false]);
+    // converging at the sphere puts it in the center of both views
+    renderer
+        .camera
+        .stereo
+        .as_mut()
+        .unwrap()
+        .convergence_distance = 5.0;
+    assert_eq!(
+        lit_pixels(&renderer)[1],
+        [false, false, true, false, false, false, false, true, false, false]
+    );
+    // with an odd width the right eye gets the extra column and sees the sphere between the
+    // two pixels in its center
+    let stereo = renderer.camera.stereo;
+    let mut renderer = glowing_spheres(&[Vec3D::default()], 1.2, 11, 3);
+    renderer.camera.stereo = stereo;
+    let row = &lit_pixels(&renderer)[1];
+    assert_eq!(row[..5], [false, false, true, false, false]);
+    assert_eq!(row[5..], [false, false, true, true, false, false]);
+
+    // omnidirectional stereo panorama of spheres in front of and behind the camera, with the
+    // left eye's 8x4 panorama on top
+    let mut renderer = glowing_spheres(&[Vec3D::default(), Vec3D::new(0.0, 0.0, 10.0)], 2.0, 8, 8);
+    renderer.camera.projection = Projection::Equirectangular;
+    renderer.camera.stereo = Some(Stereo {
+        layout: StereoLayout::TopBottom,
+        interocular_distance: 2.0,
+        convergence_distance: f64::INFINITY,
+    });
+    let panorama = lit_pixels(&renderer);
+    // both spheres appear to the right of where they are for the left eye and vice versa
+    let left_eye = [true, false, false, false, true, false, false, false];
+    let right_eye = [false, false, false, true, false, false, false, true];
+    assert_eq!(
+        panorama,
+        [
+            [false; 8], left_eye, left_eye, [false; 8], [false; 8], right_eye, right_eye,
+            [false; 8],
+        ]
+    );
+}
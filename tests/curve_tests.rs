@@ -0,0 +1,176 @@
+use simple_raytracer::materials::Hair;
+use simple_raytracer::math::vector::Vec3D;
+use simple_raytracer::objects::curve::*;
+use simple_raytracer::objects::hittables::Hittable;
+use simple_raytracer::objects::ray::Ray;
+
+use palette::LinSrgb;
+
+fn ray_down_z(x: f64, y: f64) -> Ray {
+    Ray {
+        origin: Vec3D::new(x, y, 5.0),
+        direction: Vec3D::new(0.0, 0.0, -1.0),
+        time: 0.0,
+    }
+}
+
+// Straight strand along x from -1 to 1, tapering from width 0.2 to 0
+fn tapered_strand() -> Strand {
+    Strand {
+        points: vec![Vec3D::new(-1.0, 0.0, 0.0), Vec3D::new(1.0, 0.0, 0.0)],
+        widths: vec![0.2, 0.0],
+    }
+}
+
+#[test]
+fn test_curve_intersect() {
+    let ribbon = Curves::new(&[tapered_strand()], CurveBasis::Linear, CurveShape::Ribbon);
+    assert_eq!(ribbon.segment_count(), 1);
+    let hit = ribbon
+        .intersect(ray_down_z(-0.5, 0.07), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 5.0).abs() < 1e-9);
+    assert!(hit.normal.almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+    assert!(hit.tangent.unwrap().almost_equal(Vec3D::new(1.0, 0.0, 0.0)));
+    assert!((hit.u - 0.25).abs() < 1e-9);
+    // the width at u = 0.25 is 0.15
+    assert!(((hit.v - 0.5).abs() - 0.07 / 0.15).abs() < 1e-9);
+    assert!(ribbon
+        .intersect(ray_down_z(-0.5, 0.08), 0.0, f64::INFINITY)
+        .is_none());
+    assert!(ribbon
+        .intersect(ray_down_z(1.1, 0.0), 0.0, f64::INFINITY)
+        .is_none());
+    // a ray along the strand gets a normal perpendicular to it instead of NaN
+    let along = Ray {
+        origin: Vec3D::new(-3.0, 0.0, 0.0),
+        direction: Vec3D::new(1.0, 0.0, 0.0),
+        time: 0.0,
+    };
+    let hit = ribbon.intersect(along, 0.0, f64::INFINITY).unwrap();
+    assert!(hit.normal.x.abs() < 1e-9 && (hit.normal.norm() - 1.0).abs() < 1e-9);
+
+    // a cylinder is hit before its axis, with the normal pointing away from it
+    let cylinder = Curves::new(
+        &[tapered_strand()],
+        CurveBasis::Linear,
+        CurveShape::Cylinder,
+    );
+    let hit = cylinder
+        .intersect(ray_down_z(-1.0, 0.06), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 4.92).abs() < 1e-9);
+    assert!(hit.normal.almost_equal(Vec3D::new(0.0, 0.6, 0.8)));
+    assert!(hit.front_face);
+    // rays starting inside of the hair pass through
+    let inside = Ray {
+        origin: Vec3D::new(-0.9, 0.0, 0.0),
+        direction: Vec3D::new(0.0, 1.0, 0.0),
+        time: 0.0,
+    };
+    assert!(cylinder.intersect(inside, 0.0, f64::INFINITY).is_none());
+
+    // evenly spaced B-spline points on a line give a straight segment between the middle two
+    let line = Strand {
+        points: (0..4)
+            .map(|i| Vec3D::new(2.0 * i as f64 - 3.0, 0.0, 0.0))
+            .collect(),
+        widths: vec![0.1; 4],
+    };
+    let spline = Curves::new(&[line], CurveBasis::BSpline, CurveShape::Ribbon);
+    assert_eq!(spline.segment_count(), 1);
+    let hit = spline
+        .intersect(ray_down_z(0.3, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.u - 0.65).abs() < 1e-9);
+    assert!(spline
+        .intersect(ray_down_z(1.1, 0.0), 0.0, f64::INFINITY)
+        .is_none());
+}
+
+#[test]
+fn test_curved_strands() {
+    // a B-spline arc bending up around the origin in the xz plane
+    let points: Vec<Vec3D> = (0..9)
+        .map(|i| {
+            let angle = std::f64::consts::PI * i as f64 / 8.0;
+            Vec3D::new(-f64::cos(angle), 0.0, f64::sin(angle))
+        })
+        .collect();
+    let strands: Vec<Strand> = (0..50)
+        .map(|k| Strand {
+            points: points
+                .iter()
+                .map(|p| *p + Vec3D::new(0.0, 0.1 * k as f64, 0.0))
+                .collect(),
+            widths: vec![0.02; points.len()],
+        })
+        .collect();
+    let curves = Curves::new(&strands, CurveBasis::BSpline, CurveShape::Ribbon);
+    assert_eq!(curves.segment_count(), 50 * 6);
+    let bounds = curves.bounding_box().unwrap();
+    assert!(bounds.max.y > 4.9 && bounds.max.z < 1.01);
+
+    // looking down on the top of the arc, where the B-spline lies just inside of the circle
+    let hit = curves
+        .intersect(ray_down_z(0.0, 2.0), 0.0, f64::INFINITY)
+        .unwrap();
+    let top = (4.0 + 2.0 * f64::cos(std::f64::consts::PI / 8.0)) / 6.0;
+    assert!((hit.t - (5.0 - top)).abs() < 1e-3, "{}", hit.t);
+    assert!((hit.u - 0.5).abs() < 1e-6);
+    assert!(hit.tangent.unwrap().x.abs() > 0.999);
+    // rays between the strands miss
+    assert!(curves
+        .intersect(ray_down_z(0.0, 2.05), 0.0, f64::INFINITY)
+        .is_none());
+
+    let path = std::env::temp_dir().join("simple_raytracer_strands.txt");
+    std::fs::write(
+        &path,
+        "# two hairs\n0 0 0 0.1  0 1 0 0.1\n\n1 0 0 0.1  1 1 0 0.1  1 2 0 0.05\n",
+    )
+    .unwrap();
+    let curves =
+        Curves::from_strands_file(&path, CurveBasis::Linear, CurveShape::Cylinder).unwrap();
+    assert_eq!(curves.segment_count(), 3);
+    let hit = curves
+        .intersect(ray_down_z(1.0, 1.5), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - (5.0 - 0.0375)).abs() < 1e-9);
+    std::fs::write(&path, "0 0 0 0.1 1 1\n").unwrap();
+    assert!(Curves::from_strands_file(&path, CurveBasis::Linear, CurveShape::Ribbon).is_err());
+}
+
+#[test]
+fn test_hair_scattering() {
+    let hair = Hair {
+        ior: 1.55,
+        tilt: -0.05,
+        roughness: 0.1,
+        azimuthal_roughness: 0.3,
+    };
+    let tangent = Vec3D::new(1.0, 0.0, 0.0);
+    let black = LinSrgb::new(0.0, 0.0, 0.0);
+    let white = LinSrgb::new(1.0, 1.0, 1.0);
+    let to_viewer = Vec3D::new(0.0, 0.0, 1.0);
+
+    // black hair only reflects off its surface, strongest around the mirror direction
+    let mirror = hair.eval(
+        black,
+        tangent,
+        Vec3D::new(-0.1, 0.0, 0.995).unit_vector(),
+        to_viewer,
+    );
+    let off_mirror = hair.eval(black, tangent, Vec3D::new(-0.6, 0.0, 0.8), to_viewer);
+    assert!(mirror.red > 0.0 && mirror.red == mirror.blue);
+    assert!(mirror.red > 10.0 * off_mirror.red);
+
+    // light from behind passes through the hair and is tinted by its color
+    let behind = Vec3D::new(0.0, 0.0, -1.0);
+    let red = hair.eval(LinSrgb::new(1.0, 0.0, 0.0), tangent, behind, to_viewer);
+    assert!(red.red > 10.0 * red.green);
+    assert!(
+        hair.eval(white, tangent, behind, to_viewer).red
+            > hair.eval(black, tangent, behind, to_viewer).red
+    );
+}
@@ -1,6 +1,26 @@
+use simple_raytracer::materials::{PhongModel, Texture};
+use simple_raytracer::math::polynomial::solve_quartic;
 use simple_raytracer::math::vector::Vec3D;
 use simple_raytracer::objects::{
-    hittables::*, plane::Plane, ray::Ray, sphere::Sphere, triangle::Triangle,
+    bezier::{self, BezierPatch},
+    boxes::*,
+    capsule::Capsule,
+    cone::Cone,
+    csg::Csg,
+    cylinder::Cylinder,
+    disk::Disk,
+    displacement,
+    heightfield::Heightfield,
+    hittables::*,
+    mesh::Mesh,
+    motion::*,
+    plane::Plane,
+    quad::Quad,
+    ray::Ray,
+    sdf::*,
+    sphere::Sphere,
+    torus::Torus,
+    triangle::Triangle,
 };
 
 #[test]
@@ -15,6 +35,7 @@ fn test_plane_intersect() {
     let my_ray = Ray {
         origin: Vec3D::new(1.0, 2.0, -3.0),
         direction: Vec3D::new(0.0, 0.0, 1.0),
+        time: 0.0,
     };
 
     let intersection_point = my_plane.intersect(my_ray, 0.0, f64::INFINITY);
@@ -31,6 +52,7 @@ fn test_plane_intersect() {
     let my_ray = Ray {
         origin: Vec3D::new(-1.0, 4.0, 1.0),
         direction: Vec3D::new(2.0, -5.0, 1.0),
+        time: 0.0,
     };
     #[allow(unused)]
     let intersection_point = my_plane.intersect(my_ray, 0.0, f64::INFINITY);
@@ -47,6 +69,7 @@ fn test_sphere_intersect() {
     let my_ray = Ray {
         origin: Vec3D::new(0.0, 0.0, 5.0),
         direction: Vec3D::new(0.0, 0.0, -1.0),
+        time: 0.0,
     };
     let intersection_point = my_sphere.intersect(my_ray, 0.0, f64::INFINITY).unwrap();
     //eprintln!("{}", intersection_point);
@@ -67,9 +90,751 @@ fn test_triangle_intersect() {
     let ray1 = Ray {
         origin: Vec3D::new(0.25, 0.25, 5.0),
         direction: Vec3D::new(0.0, 0.0, -1.0),
+        time: 0.0,
     };
-    let t = my_triangle
+    let (t, u, v) = my_triangle
         .get_intersection(ray1, 0.0, f64::INFINITY)
         .unwrap();
     assert_eq!(t, 5.0);
+    // barycentric coordinates of vert_a and vert_b
+    assert_eq!((u, v), (0.5, 0.25));
+    assert!(my_triangle
+        .point_at(u, v)
+        .almost_equal(Vec3D::new(0.25, 0.25, 0.0)));
+    // hit from behind
+    let ray2 = Ray {
+        origin: Vec3D::new(0.25, 0.25, -5.0),
+        direction: Vec3D::new(0.0, 0.0, 1.0),
+        time: 0.0,
+    };
+    assert!(my_triangle
+        .get_intersection(ray2, 0.0, f64::INFINITY)
+        .is_some());
+    assert!(my_triangle.get_intersection(ray1, 0.0, 4.0).is_none());
+
+    // rays through the diagonal shared by two triangles always hit one of them
+    let corners = [
+        Vec3D::new(-0.3, 0.1, 0.7),
+        Vec3D::new(1.1, -0.2, 0.3),
+        Vec3D::new(0.9, 1.3, -0.4),
+        Vec3D::new(-0.1, 0.8, 0.1),
+    ];
+    let quad = [[0, 1, 2], [0, 2, 3]].map(|[a, b, c]| Triangle {
+        vert_a: corners[a],
+        vert_b: corners[b],
+        vert_c: corners[c],
+        normal: None,
+        uv: None,
+    });
+    let origin = Vec3D::new(0.2, 0.3, 3.0);
+    for i in 1..1000 {
+        let on_diagonal = corners[0] + (i as f64 / 1000.0) * (corners[2] - corners[0]);
+        let ray = Ray {
+            origin,
+            direction: on_diagonal - origin,
+            time: 0.0,
+        };
+        assert!(quad
+            .iter()
+            .any(|triangle| triangle.get_intersection(ray, 0.0, f64::INFINITY).is_some()));
+    }
+}
+
+#[test]
+fn test_mesh_texture_coordinates() {
+    use rand::{rngs::SmallRng, SeedableRng};
+    use simple_raytracer::objects::mesh::Mesh;
+    // OBJ texture coordinates start at the bottom of the image, textures are looked up with
+    // v pointing down
+    let path = std::env::temp_dir().join("simple_raytracer_uv_triangle.obj");
+    std::fs::write(
+        &path,
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvn 0 0 1\nf 1/1/1 2/2/1 3/3/1\n",
+    )
+    .unwrap();
+    let mesh = Mesh::from_file(&path).unwrap();
+    let ray = Ray {
+        origin: Vec3D::new(0.25, 0.125, 5.0),
+        direction: Vec3D::new(0.0, 0.0, -1.0),
+        time: 0.0,
+    };
+    let hit = mesh.intersect(ray, 0.0, f64::INFINITY).unwrap();
+    assert!((hit.u - 0.25).abs() < 1e-9 && (hit.v - 0.875).abs() < 1e-9);
+
+    // points sampled on emissive meshes are looked up the same way
+    let mut rng = SmallRng::seed_from_u64(0);
+    for _ in 0..10 {
+        let sample = mesh.sample_surface(&mut rng, Vec3D::default()).unwrap();
+        assert!((sample.u - sample.point.x).abs() < 1e-9);
+        assert!((sample.v - (1.0 - sample.point.y)).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_alpha_cutout() {
+    // 1x2 texture, transparent in the top row and opaque in the bottom row
+    let path = std::env::temp_dir().join("simple_raytracer_alpha_cutout.png");
+    image::RgbaImage::from_vec(1, 2, vec![255, 255, 255, 0, 255, 255, 255, 255])
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let opacity = Texture::load_texture(&path).unwrap();
+    assert_eq!(opacity.get_alpha_at(0.5, 0.25), 0.0);
+    assert_eq!(opacity.get_alpha_at(0.5, 0.75), 1.0);
+
+    // On the xy-plane v = -x (mod 1)
+    let my_plane = Plane {
+        normal: Vec3D::new(0.0, 0.0, 1.0),
+        distance: 0.0,
+        phong_data: Some(PhongModel {
+            opacity: Some(opacity),
+            ..PhongModel::new()
+        }),
+    };
+    let ray_at = |x: f64| Ray {
+        origin: Vec3D::new(x, 0.5, -3.0),
+        direction: Vec3D::new(0.0, 0.0, 1.0),
+        time: 0.0,
+    };
+    assert!(my_plane
+        .intersect(ray_at(-0.25), 0.0, f64::INFINITY)
+        .is_none());
+    assert!(my_plane
+        .intersect(ray_at(-0.75), 0.0, f64::INFINITY)
+        .is_some());
+}
+
+fn ray_down_z(x: f64, y: f64) -> Ray {
+    Ray {
+        origin: Vec3D::new(x, y, 5.0),
+        direction: Vec3D::new(0.0, 0.0, -1.0),
+        time: 0.0,
+    }
+}
+
+#[test]
+fn test_box_intersect() {
+    let aa_box = AxisAlignedBox {
+        min: Vec3D::new(-1.0, -1.0, -1.0),
+        max: Vec3D::new(1.0, 1.0, 1.0),
+        phong_data: None,
+    };
+    let hit = aa_box
+        .intersect(ray_down_z(0.5, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 4.0);
+    assert!(hit.front_face);
+    assert!(hit.normal.almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+    assert!((hit.u - 0.75).abs() < 1e-9 && (hit.v - 0.5).abs() < 1e-9);
+    // leaving the box from the inside
+    let hit = aa_box
+        .intersect(ray_down_z(0.5, 0.0), 4.5, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 6.0);
+    assert!(!hit.front_face);
+    assert!(aa_box
+        .intersect(ray_down_z(1.5, 0.0), 0.0, f64::INFINITY)
+        .is_none());
+
+    // the same box rotated 45 degrees around z is hit further out along the diagonal
+    let rotated = OrientedBox::new(
+        Vec3D::default(),
+        Vec3D::new(1.0, 1.0, 1.0),
+        Vec3D::new(1.0, 1.0, 0.0),
+        Vec3D::new(-1.0, 1.0, 0.0),
+    );
+    assert!(rotated
+        .intersect(ray_down_z(1.2, 0.0), 0.0, f64::INFINITY)
+        .is_some());
+    let hit = rotated
+        .intersect(
+            Ray {
+                origin: Vec3D::new(5.0, 0.0, 0.0),
+                direction: Vec3D::new(-1.0, 0.0, 0.0),
+                time: 0.0,
+            },
+            0.0,
+            f64::INFINITY,
+        )
+        .unwrap();
+    assert!((hit.t - (5.0 - f64::sqrt(2.0))).abs() < 1e-9);
+    let bounds = rotated.bounding_box().unwrap();
+    assert!((bounds.max.x - f64::sqrt(2.0)).abs() < 1e-9 && (bounds.max.z - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_flat_primitives_intersect() {
+    let disk = Disk {
+        center: Vec3D::default(),
+        normal: Vec3D::new(0.0, 0.0, 1.0),
+        radius: 1.0,
+        phong_data: None,
+    };
+    let hit = disk
+        .intersect(ray_down_z(0.5, 0.5), 0.0, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 5.0);
+    assert!((hit.v - f64::sqrt(0.5)).abs() < 1e-9);
+    assert!(disk
+        .intersect(ray_down_z(0.8, 0.8), 0.0, f64::INFINITY)
+        .is_none());
+
+    let quad = Quad::rectangle(
+        Vec3D::default(),
+        Vec3D::new(1.0, 0.0, 0.0),
+        Vec3D::new(0.0, 1.0, 0.0),
+        2.0,
+        1.0,
+    );
+    let hit = quad
+        .intersect(ray_down_z(0.5, 0.25), 0.0, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 5.0);
+    assert!(hit.front_face);
+    assert!((hit.u - 0.75).abs() < 1e-9 && (hit.v - 0.75).abs() < 1e-9);
+    assert!(quad
+        .intersect(ray_down_z(0.5, 0.75), 0.0, f64::INFINITY)
+        .is_none());
+    assert_eq!(quad.area(), 2.0);
+}
+
+#[test]
+fn test_cylinder_and_cone_intersect() {
+    // both stand on the xy-plane along +z
+    let cylinder = Cylinder::between(Vec3D::default(), Vec3D::new(0.0, 0.0, 2.0), 1.0);
+    let hit = cylinder
+        .intersect(ray_down_z(0.5, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 3.0);
+    assert!(hit.normal.almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+    let side_ray = Ray {
+        origin: Vec3D::new(5.0, 0.0, 1.0),
+        direction: Vec3D::new(-1.0, 0.0, 0.0),
+        time: 0.0,
+    };
+    let hit = cylinder.intersect(side_ray, 0.0, f64::INFINITY).unwrap();
+    assert!((hit.t - 4.0).abs() < 1e-9);
+    assert!(hit.normal.almost_equal(Vec3D::new(1.0, 0.0, 0.0)));
+    assert!((hit.v - 0.5).abs() < 1e-9);
+
+    let cone = Cone::new(Vec3D::default(), Vec3D::new(0.0, 0.0, 2.0), 1.0);
+    // halfway up the radius is 0.5
+    let hit = cone.intersect(side_ray, 0.0, f64::INFINITY).unwrap();
+    assert!((hit.t - 4.5).abs() < 1e-9);
+    let expected_normal = Vec3D::new(2.0, 0.0, 1.0).unit_vector();
+    assert!((hit.normal - expected_normal).norm() < 1e-9);
+    // from below the base cap is hit
+    let hit = cone
+        .intersect(
+            Ray {
+                origin: Vec3D::new(0.5, 0.0, -1.0),
+                direction: Vec3D::new(0.0, 0.0, 1.0),
+                time: 0.0,
+            },
+            0.0,
+            f64::INFINITY,
+        )
+        .unwrap();
+    assert!((hit.t - 1.0).abs() < 1e-9);
+    assert!(hit.normal.almost_equal(Vec3D::new(0.0, 0.0, -1.0)));
+    assert!(cone
+        .intersect(ray_down_z(0.8, 0.0), 0.0, f64::INFINITY)
+        .is_some());
+    let bounds = cone.bounding_box().unwrap();
+    assert!(bounds.min.almost_equal(Vec3D::new(-1.0, -1.0, 0.0)));
+    assert!(bounds.max.almost_equal(Vec3D::new(1.0, 1.0, 2.0)));
+}
+
+#[test]
+fn test_torus_and_capsule_intersect() {
+    // torus lying in the xy-plane
+    let torus = Torus {
+        center: Vec3D::default(),
+        axis: Vec3D::new(0.0, 0.0, 1.0),
+        major_radius: 2.0,
+        minor_radius: 0.5,
+        phong_data: None,
+    };
+    let hit = torus
+        .intersect(ray_down_z(2.0, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 4.5).abs() < 1e-9);
+    assert!((hit.normal - Vec3D::new(0.0, 0.0, 1.0)).norm() < 1e-6);
+    // through the hole
+    assert!(torus
+        .intersect(ray_down_z(0.0, 0.0), 0.0, f64::INFINITY)
+        .is_none());
+    // along the x axis the ray crosses the tube four times
+    let ray = Ray {
+        origin: Vec3D::new(-5.0, 0.0, 0.0),
+        direction: Vec3D::new(1.0, 0.0, 0.0),
+        time: 0.0,
+    };
+    let mut t = 0.0;
+    let mut hits = Vec::new();
+    while let Some(hit) = torus.intersect(ray, t, f64::INFINITY) {
+        hits.push(hit.t);
+        t = hit.t + 1e-6;
+    }
+    let expected = [2.5, 3.5, 6.5, 7.5];
+    assert_eq!(hits.len(), 4);
+    assert!(hits.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-6));
+
+    let capsule = Capsule {
+        start: Vec3D::new(-1.0, 0.0, 0.0),
+        end: Vec3D::new(1.0, 0.0, 0.0),
+        radius: 0.5,
+        phong_data: None,
+    };
+    let hit = capsule.intersect(ray, 0.0, f64::INFINITY).unwrap();
+    assert!((hit.t - 3.5).abs() < 1e-9);
+    assert!(hit.normal.almost_equal(Vec3D::new(-1.0, 0.0, 0.0)));
+    assert!(hit.v.abs() < 1e-9);
+    let hit = capsule
+        .intersect(ray_down_z(0.0, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 4.5);
+    assert!((hit.v - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_solve_quartic() {
+    // (x - 1)(x - 2)(x + 3)(x - 0.5) expanded
+    let roots = solve_quartic(1.0, -0.5, -7.0, 9.5, -3.0);
+    let expected = [-3.0, 0.5, 1.0, 2.0];
+    assert_eq!(roots.len(), 4);
+    assert!(roots
+        .iter()
+        .zip(expected)
+        .all(|(a, b)| (a - b).abs() < 1e-9));
+    // x^4 + 1 has no real roots
+    assert!(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0).is_empty());
+}
+
+#[test]
+fn test_csg_intersect() {
+    use simple_raytracer::materials::Material;
+    use std::sync::Arc;
+    let colored = |r: f32| {
+        Some(PhongModel {
+            material: Material::Color(palette::LinSrgb::new(r, 0.0, 0.0)),
+            ..PhongModel::new()
+        })
+    };
+    let sphere = Arc::new(Sphere {
+        center: Vec3D::default(),
+        radius: 2.0,
+        phong_data: colored(1.0),
+    });
+    let cube = Arc::new(AxisAlignedBox {
+        min: Vec3D::new(-1.0, -1.0, 0.0),
+        max: Vec3D::new(1.0, 1.0, 3.0),
+        phong_data: colored(0.5),
+    });
+
+    // the box bites a square hole into the top of the sphere, the floor of the hole is the
+    // bottom face of the box with its normal flipped out of the remaining solid
+    let difference = Csg::difference(sphere.clone(), cube.clone());
+    let hit = difference
+        .intersect(ray_down_z(0.0, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 5.0);
+    assert!(hit.front_face);
+    assert!(hit.normal.almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+    assert!(std::ptr::eq(
+        hit.phong_data.unwrap(),
+        cube.phong_data.as_ref().unwrap()
+    ));
+    // next to the hole the sphere surface is unchanged
+    let hit = difference
+        .intersect(ray_down_z(1.5, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - (5.0 - f64::sqrt(1.75))).abs() < 1e-9);
+    assert!(std::ptr::eq(
+        hit.phong_data.unwrap(),
+        sphere.phong_data.as_ref().unwrap()
+    ));
+
+    // the union has no surfaces inside of the sphere, so the ray leaves through the box top
+    let union = Csg::union(sphere.clone(), cube.clone());
+    let hit = union
+        .intersect(ray_down_z(0.0, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 2.0);
+    let hit = union
+        .intersect(ray_down_z(0.0, 0.0), 2.5, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 7.0);
+    assert!(!hit.front_face);
+
+    // the intersection is entered through the sphere and left through the box bottom
+    let intersection = Csg::intersection(sphere, cube);
+    let hit = intersection
+        .intersect(ray_down_z(0.0, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 3.0);
+    let hit = intersection
+        .intersect(ray_down_z(0.0, 0.0), 3.5, f64::INFINITY)
+        .unwrap();
+    assert_eq!(hit.t, 5.0);
+    assert!(!hit.front_face);
+    assert!(hit.normal.almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+    let bounds = intersection.bounding_box().unwrap();
+    assert!(bounds.min.almost_equal(Vec3D::new(-1.0, -1.0, 0.0)));
+    assert!(bounds.max.almost_equal(Vec3D::new(1.0, 1.0, 2.0)));
+
+    // cutting a unit sphere with the half-space below a plane leaves the lower hemisphere, also
+    // for rays starting behind the plane that run parallel to it or away from it
+    let hemisphere = Csg::intersection(
+        Arc::new(Sphere {
+            center: Vec3D::default(),
+            radius: 1.0,
+            phong_data: None,
+        }),
+        Arc::new(Plane {
+            normal: Vec3D::new(0.0, 1.0, 0.0),
+            distance: 0.0,
+            phong_data: None,
+        }),
+    );
+    for direction in [Vec3D::new(0.0, 0.0, -1.0), Vec3D::new(0.0, -0.05, -1.0)] {
+        let ray = Ray {
+            origin: Vec3D::new(0.0, -0.5, 5.0),
+            direction,
+            time: 0.0,
+        };
+        let hit = hemisphere.intersect(ray, 0.0, f64::INFINITY).unwrap();
+        assert!(hit.front_face);
+        assert!(ray.at(hit.t).y < 0.0);
+    }
+    // above the plane there is nothing
+    assert!(hemisphere
+        .intersect(ray_down_z(0.0, 0.5), 0.0, f64::INFINITY)
+        .is_none());
+}
+
+#[test]
+fn test_csg_cutout() {
+    use std::sync::Arc;
+    // the upper half of the sphere is cut out
+    let path = std::env::temp_dir().join("simple_raytracer_csg_cutout.png");
+    image::RgbaImage::from_vec(1, 2, vec![255, 255, 255, 0, 255, 255, 255, 255])
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let sphere = Arc::new(Sphere {
+        center: Vec3D::default(),
+        radius: 2.0,
+        phong_data: Some(PhongModel {
+            opacity: Some(Texture::load_texture(&path).unwrap()),
+            ..PhongModel::new()
+        }),
+    });
+    let cube = Arc::new(AxisAlignedBox {
+        min: Vec3D::new(-1.0, -1.0, 0.0),
+        max: Vec3D::new(1.0, 1.0, 3.0),
+        phong_data: Some(PhongModel::new()),
+    });
+    let intersection = Csg::intersection(sphere, cube.clone());
+    // the ray still enters the sphere through the cut out part, so it leaves the intersection
+    // through the box bottom
+    let ray = ray_down_z(0.0, 0.5);
+    let hit = intersection.intersect(ray, 0.0, f64::INFINITY).unwrap();
+    assert_eq!(hit.t, 5.0);
+    assert!(!hit.front_face);
+    assert!(std::ptr::eq(
+        hit.phong_data.unwrap(),
+        cube.phong_data.as_ref().unwrap()
+    ));
+    // the crossing into the sphere is only reported when asking for the solid
+    let entry = intersection
+        .intersect_solid(ray, 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((entry.t - (5.0 - f64::sqrt(3.75))).abs() < 1e-9);
+    assert!(entry.front_face);
+}
+
+#[test]
+fn test_sdf_intersect() {
+    use std::sync::Arc;
+    let sphere = SdfObject::new(Arc::new(SdfSphere {
+        center: Vec3D::default(),
+        radius: 2.0,
+    }));
+    let hit = sphere
+        .intersect(ray_down_z(0.0, 0.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 3.0).abs() < 1e-4);
+    assert!(hit.front_face);
+    assert!((hit.normal - Vec3D::new(0.0, 0.0, 1.0)).norm() < 1e-4);
+    // from the inside the ray marches to where it leaves the sphere
+    let hit = sphere
+        .intersect(ray_down_z(0.0, 0.0), 4.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 7.0).abs() < 1e-4);
+    assert!(!hit.front_face);
+    assert!(sphere
+        .intersect(ray_down_z(2.5, 0.0), 0.0, f64::INFINITY)
+        .is_none());
+
+    // closures work as distance functions, here an unbounded slab |z| < 0.5
+    let slab = SdfObject::new(Arc::new(|p: Vec3D| p.z.abs() - 0.5));
+    let hit = slab
+        .intersect(ray_down_z(10.0, 3.0), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 4.5).abs() < 1e-4);
+
+    // smoothly joined spheres fill in the gap between them
+    let blob = SdfObject::new(Arc::new(SdfUnion {
+        a: Arc::new(SdfSphere {
+            center: Vec3D::new(-1.0, 0.0, 0.0),
+            radius: 0.9,
+        }),
+        b: Arc::new(SdfSphere {
+            center: Vec3D::new(1.0, 0.0, 0.0),
+            radius: 0.9,
+        }),
+        smoothness: 0.5,
+    }));
+    assert!(blob
+        .intersect(ray_down_z(0.0, 0.0), 0.0, f64::INFINITY)
+        .is_some());
+    assert_eq!(smooth_min(1.0, 3.0, 0.5), 1.0);
+    assert!(smooth_min(1.0, 1.0, 0.5) < 1.0);
+}
+
+#[test]
+fn test_heightfield_intersect() {
+    // ramp rising along x from 0 to 2 over a 4x4 square, sampled at 5x3 points
+    let heights: Vec<f64> = (0..3)
+        .flat_map(|_| (0..5).map(|x| 0.5 * x as f64))
+        .collect();
+    let terrain = Heightfield::new(Vec3D::new(-2.0, 0.0, -2.0), (4.0, 4.0), (5, 3), heights);
+    let down = |x: f64, z: f64| Ray {
+        origin: Vec3D::new(x, 5.0, z),
+        direction: Vec3D::new(0.0, -1.0, 0.0),
+        time: 0.0,
+    };
+    let hit = terrain
+        .intersect(down(0.5, 0.3), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 3.75).abs() < 1e-9);
+    assert!(hit.front_face);
+    assert!((hit.normal - Vec3D::new(-0.5, 1.0, 0.0).unit_vector()).norm() < 1e-9);
+    assert!((hit.u - 0.625).abs() < 1e-9 && (hit.v - 0.575).abs() < 1e-9);
+    assert_eq!(terrain.get_height_at(0.5, 0.3), Some(1.25));
+    assert!(terrain
+        .intersect(down(2.5, 0.0), 0.0, f64::INFINITY)
+        .is_none());
+
+    // a grazing ray travels over several cells before it hits the slope
+    let ray = Ray {
+        origin: Vec3D::new(-3.0, 1.0, 0.7),
+        direction: Vec3D::new(1.0, 0.0, 0.1),
+        time: 0.0,
+    };
+    let hit = terrain.intersect(ray, 0.0, f64::INFINITY).unwrap();
+    assert!((hit.t - 3.0).abs() < 1e-9);
+
+    // an image with a bright pixel in the top left corner, which ends up at min x, max z
+    let path = std::env::temp_dir().join("simple_raytracer_heightfield.png");
+    image::GrayImage::from_vec(2, 2, vec![255, 0, 0, 0])
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let terrain = Heightfield::from_image(&path, Vec3D::default(), (1.0, 1.0), 2.0).unwrap();
+    assert_eq!(terrain.get_height_at(0.0, 1.0), Some(2.0));
+    assert_eq!(terrain.get_height_at(1.0, 0.0), Some(0.0));
+}
+
+#[test]
+fn test_bezier_patch() {
+    // a flat patch with evenly spaced control points maps (u, v) to (3u, 3v, 0), followed
+    // by one bulging up in the middle
+    let mut bpt = String::from("2\n3 3\n");
+    for i in 0..4 {
+        for j in 0..4 {
+            bpt += &format!("{j} {i} 0\n");
+        }
+    }
+    bpt += "3 3\n";
+    for i in 0..4 {
+        for j in 0..4 {
+            let z = if (1..3).contains(&i) && (1..3).contains(&j) {
+                1.0
+            } else {
+                0.0
+            };
+            bpt += &format!("{} {} {z}\n", j + 4, i);
+        }
+    }
+    let path = std::env::temp_dir().join("simple_raytracer_patches.bpt");
+    std::fs::write(&path, bpt).unwrap();
+    let patches = BezierPatch::load_bpt(&path).unwrap();
+    assert_eq!(patches.len(), 2);
+
+    let flat = patches[0];
+    assert!(flat
+        .point_at(0.5, 0.25)
+        .almost_equal(Vec3D::new(1.5, 0.75, 0.0)));
+    assert!(flat
+        .get_normal_at(0.3, 0.6)
+        .almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+    assert_eq!(flat.divisions_for(0.01), (1, 1));
+    // the bulge reaches (3/4)^2 of the control point height in the middle
+    let bulge = patches[1];
+    assert!((bulge.point_at(0.5, 0.5).z - 0.5625).abs() < 1e-12);
+    let (nu, nv) = bulge.divisions_for(0.01);
+    assert!(nu > 1 && nu == nv);
+
+    let mesh = bezier::tessellate(&patches, 0.01);
+    assert_eq!(mesh.triangle_count(), 2 * 2 * nu * nv);
+    let hit = mesh
+        .intersect(ray_down_z(1.5, 0.75), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 5.0).abs() < 1e-9);
+    assert!((hit.u - 0.5).abs() < 1e-9 && (hit.v - 0.25).abs() < 1e-9);
+    let hit = mesh
+        .intersect(ray_down_z(5.5, 1.5), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 4.4375).abs() < 0.01);
+}
+
+#[test]
+fn test_displaced_mesh() {
+    // a 2x1 height map, white on the left and black on the right, so between the texel
+    // centers the height falls linearly from 1 at u = 0.25 to 0 at u = 0.75
+    let path = std::env::temp_dir().join("simple_raytracer_displacement.png");
+    image::GrayImage::from_vec(2, 1, vec![255, 0])
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let height_map = Texture::load_texture(&path).unwrap();
+    assert!((height_map.get_height_at(0.5, 0.5) - 0.5).abs() < 1e-9);
+
+    // a flat 2x2 square in the xy plane with u along x
+    let corners = [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+    let vertex = |i: usize| Vec3D::new(corners[i].0, corners[i].1, 0.0);
+    let uv = |i: usize| (corners[i].0 / 2.0, corners[i].1 / 2.0);
+    let square = Mesh::new(
+        [[0, 1, 2], [0, 2, 3]]
+            .iter()
+            .map(|&[a, b, c]| Triangle {
+                vert_a: vertex(a),
+                vert_b: vertex(b),
+                vert_c: vertex(c),
+                normal: None,
+                uv: Some([uv(a), uv(b), uv(c)]),
+            })
+            .collect(),
+    );
+    let displaced = displacement::displace(&square, &height_map, 0.5, 1e-3, 0.25);
+    assert!(displaced.triangle_count() > 2 * 64);
+    for triangle in displaced.triangles() {
+        let flat = |p: Vec3D| Vec3D::new(p.x, p.y, 0.0);
+        let (a, b, c) = (
+            flat(triangle.vert_a),
+            flat(triangle.vert_b),
+            flat(triangle.vert_c),
+        );
+        assert!((a - b).norm() <= 0.25 && (b - c).norm() <= 0.25 && (c - a).norm() <= 0.25);
+    }
+
+    // inside the linear part of the height map the displaced surface is exact
+    let hit = displaced
+        .intersect(ray_down_z(1.2, 0.7), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - (5.0 - 0.5 * 0.3)).abs() < 1e-9);
+    assert!((hit.u - 0.6).abs() < 1e-9 && (hit.v - 0.65).abs() < 1e-9);
+    // the slope tilts the normals towards +x
+    let slope = Vec3D::new(0.5, 0.0, 1.0).unit_vector();
+    assert!((hit.normal - slope).norm() < 1e-9);
+
+    // a bump in the middle of an otherwise flat 8x1 height map
+    image::GrayImage::from_vec(8, 1, vec![0, 0, 0, 255, 255, 0, 0, 0])
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let bump = Texture::load_texture(&path).unwrap();
+    let adaptive = displacement::displace(&square, &bump, 0.5, 1e-3, 0.5);
+    let uniform = displacement::displace(&square, &bump, 0.5, f64::INFINITY, 0.25);
+    assert!(adaptive.triangle_count() < uniform.triangle_count());
+    // the flat parts stay at the size of the cap, the bump is refined beyond it
+    let x_extent = |triangle: &Triangle| {
+        let xs = [triangle.vert_a.x, triangle.vert_b.x, triangle.vert_c.x];
+        let min = xs.into_iter().fold(2.0, f64::min);
+        (min, xs.into_iter().fold(0.0, f64::max) - min)
+    };
+    for triangle in adaptive.triangles() {
+        let (min, extent) = x_extent(&triangle);
+        if min + extent <= 0.5 || min >= 1.5 {
+            assert!(extent >= 0.25);
+        }
+    }
+    assert!(adaptive
+        .triangles()
+        .any(|triangle| x_extent(&triangle).1 < 0.25));
+}
+
+#[test]
+fn test_moving_objects() {
+    let sphere = || -> std::sync::Arc<dyn Hittable + Send + Sync> {
+        std::sync::Arc::new(Sphere {
+            center: Vec3D::default(),
+            radius: 1.0,
+            phong_data: None,
+        })
+    };
+    let at_time = |x: f64, time: f64| Ray {
+        time,
+        ..ray_down_z(x, 0.0)
+    };
+
+    // moving along x at 2 units per unit of time
+    let linear = Moving {
+        object: sphere(),
+        motion: Motion::Linear {
+            velocity: Vec3D::new(2.0, 0.0, 0.0),
+        },
+    };
+    assert!(linear.bounding_box().is_none());
+    assert!(linear
+        .intersect(at_time(3.0, 0.0), 0.0, f64::INFINITY)
+        .is_none());
+    let hit = linear
+        .intersect(at_time(3.0, 1.5), 0.0, f64::INFINITY)
+        .unwrap();
+    assert!((hit.t - 4.0).abs() < 1e-9);
+    assert_eq!(hit.ray.origin, Vec3D::new(3.0, 0.0, 5.0));
+
+    // a sphere moving up and turning a quarter around y, held after the last keyframe
+    let keyframed = Moving {
+        object: sphere(),
+        motion: Motion::from_keyframes(&[
+            Keyframe {
+                time: 0.0,
+                translation: Vec3D::default(),
+                rotation: Vec3D::default(),
+            },
+            Keyframe {
+                time: 1.0,
+                translation: Vec3D::new(0.0, 0.0, 2.0),
+                rotation: Vec3D::new(0.0, std::f64::consts::FRAC_PI_2, 0.0),
+            },
+        ]),
+    };
+    for (time, t) in [(-1.0, 4.0), (0.5, 3.0), (1.0, 2.0), (7.0, 2.0)] {
+        let hit = keyframed
+            .intersect(at_time(0.0, time), 0.0, f64::INFINITY)
+            .unwrap();
+        assert!((hit.t - t).abs() < 1e-9);
+        assert!(hit.normal.almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+    }
+    let bounds = keyframed.bounding_box().unwrap();
+    assert!(bounds.min.z <= -1.0 && bounds.max.z >= 3.0);
+
+    // the rotation turns the surface with the object
+    let (translation, rotation) = keyframed.motion.transform_at(1.0);
+    assert!(translation.almost_equal(Vec3D::new(0.0, 0.0, 2.0)));
+    assert!(rotate(Vec3D::new(1.0, 0.0, 0.0), rotation).almost_equal(Vec3D::new(0.0, 0.0, -1.0)));
 }
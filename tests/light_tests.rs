@@ -0,0 +1,309 @@
+use simple_raytracer::light::*;
+use simple_raytracer::materials::{Material, PhongModel, Subsurface};
+use simple_raytracer::math::vector::Vec3D;
+use simple_raytracer::objects::{ray::Ray, sphere::Sphere};
+use simple_raytracer::scene::Scene;
+
+use palette::LinSrgb;
+use rand::{rngs::SmallRng, SeedableRng};
+use std::sync::Arc;
+
+#[test]
+fn test_point_light_falloff() {
+    let light = PointLight {
+        position: Vec3D::new(0.0, 2.0, 0.0),
+        color: LinSrgb::new(1.0, 1.0, 1.0),
+        intensity: 8.0,
+    };
+    let sample = light.sample(Vec3D::default(), 0.0).unwrap();
+    assert_eq!(sample.distance, 2.0);
+    assert_eq!(sample.direction, Vec3D::new(0.0, 1.0, 0.0));
+    assert_eq!(sample.radiance.red, 2.0); // 8 / 2^2
+}
+
+#[test]
+fn test_directional_light() {
+    let light = DirectionalLight {
+        direction: Vec3D::new(0.0, -2.0, 0.0),
+        color: LinSrgb::new(1.0, 0.5, 0.0),
+        irradiance: 2.0,
+    };
+    let sample = light.sample(Vec3D::new(5.0, 1.0, -3.0), 0.0).unwrap();
+    assert!(sample.distance.is_infinite());
+    assert_eq!(sample.direction, Vec3D::new(0.0, 1.0, 0.0));
+    assert_eq!(sample.radiance.green, 1.0);
+}
+
+#[test]
+fn test_spot_light_cone() {
+    let light = SpotLight {
+        position: Vec3D::new(0.0, 1.0, 0.0),
+        direction: Vec3D::new(0.0, -1.0, 0.0),
+        color: LinSrgb::new(1.0, 1.0, 1.0),
+        intensity: 1.0,
+        cone_angle: f64::to_radians(30.0),
+        falloff: f64::to_radians(10.0),
+    };
+    // straight below the light: full intensity
+    assert_eq!(
+        light.sample(Vec3D::default(), 0.0).unwrap().radiance.red,
+        1.0
+    );
+    // outside of the cone: no light
+    assert!(light.sample(Vec3D::new(1.0, 0.0, 0.0), 0.0).is_none());
+    // within the falloff region: partially lit
+    let angle = f64::to_radians(25.0);
+    let point = Vec3D::new(angle.tan(), 0.0, 0.0);
+    let radiance = light.sample(point, 0.0).unwrap().radiance.red;
+    let distance2 = (1.0 + angle.tan().powi(2)) as f32;
+    assert!(0.0 < radiance * distance2 && radiance * distance2 < 1.0);
+}
+
+#[test]
+fn test_colored_shadow() {
+    // a white floor lit from straight above, with a sphere in between
+    let floor = Arc::new(Sphere {
+        center: Vec3D::new(0.0, -100.0, 0.0),
+        radius: 100.0,
+        phong_data: Some(PhongModel {
+            material: Material::Color(LinSrgb::new(1.0, 1.0, 1.0)),
+            k_d: 1.0,
+            ..PhongModel::new()
+        }),
+    });
+    let floor_color = |k_t: f32| {
+        let blocker = Arc::new(Sphere {
+            center: Vec3D::new(0.0, 1.0, 0.0),
+            radius: 0.5,
+            phong_data: Some(PhongModel {
+                material: Material::Color(LinSrgb::new(1.0, 0.5, 0.0)),
+                k_t,
+                ..PhongModel::new()
+            }),
+        });
+        let scene = Scene {
+            objects: vec![floor.clone(), blocker],
+            light_sources: vec![Arc::new(PointLight {
+                position: Vec3D::new(0.0, 3.0, 0.0),
+                color: LinSrgb::new(1.0, 1.0, 1.0),
+                intensity: 9.0,
+            })],
+            max_depth: 1,
+            sky: None,
+            area_light_samples: 0,
+            volumes: vec![],
+        };
+        let ray = Ray {
+            origin: Vec3D::new(3.0, 1.0, 0.0),
+            direction: Vec3D::new(-3.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        scene.trace(ray, 0, &mut SmallRng::seed_from_u64(0))
+    };
+    // an opaque sphere casts a black shadow, a transmissive one tints the light by its color
+    assert_eq!(floor_color(0.0), LinSrgb::new(0.0, 0.0, 0.0));
+    let color = floor_color(1.0);
+    assert!((color.red - 1.0).abs() < 1e-3, "{:?}", color);
+    assert!((color.green - 0.5).abs() < 1e-3, "{:?}", color);
+    assert_eq!(color.blue, 0.0);
+}
+
+// Clear glass sphere of radius 1.5 around the origin that absorbs red the most
+fn absorbing_sphere() -> Arc<Sphere> {
+    Arc::new(Sphere {
+        center: Vec3D::default(),
+        radius: 1.5,
+        phong_data: Some(PhongModel {
+            material: Material::Color(LinSrgb::new(1.0, 1.0, 1.0)),
+            k_t: 1.0,
+            ior: 1.0,
+            absorption: LinSrgb::new(0.4, 0.2, 0.0),
+            ..PhongModel::new()
+        }),
+    })
+}
+
+fn absorbed_over(distance: f64) -> [f32; 3] {
+    [0.4, 0.2, 0.0].map(|sigma: f64| f64::exp(-sigma * distance) as f32)
+}
+
+// secondary rays start slightly off the surface, so the distances are a bit short
+fn close_to(color: LinSrgb, expected: [f32; 3]) -> bool {
+    (color.red - expected[0]).abs() < 1e-4
+        && (color.green - expected[1]).abs() < 1e-4
+        && (color.blue - expected[2]).abs() < 1e-4
+}
+
+#[test]
+fn test_absorption() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut scene = Scene {
+        objects: vec![absorbing_sphere()],
+        light_sources: vec![],
+        max_depth: 4,
+        sky: None,
+        area_light_samples: 0,
+        volumes: vec![],
+    };
+
+    // shadow rays through the center cross a distance of 2r, rays off center less
+    let shadow_ray = |x: f64| Ray {
+        origin: Vec3D::new(x, 0.0, -5.0),
+        direction: Vec3D::new(0.0, 0.0, 2.0),
+        time: 0.0,
+    };
+    let transmittance = scene.transmittance(shadow_ray(0.0), 0.0, f64::INFINITY, &mut rng);
+    assert!(close_to(transmittance, absorbed_over(3.0)));
+    let chord = 2.0 * f64::sqrt(1.5 * 1.5 - 0.9 * 0.9);
+    let transmittance = scene.transmittance(shadow_ray(0.9), 0.0, f64::INFINITY, &mut rng);
+    assert!(close_to(transmittance, absorbed_over(chord)));
+
+    // a camera ray through the sphere sees a white light behind it tinted the same way
+    scene.objects.push(Arc::new(Sphere {
+        center: Vec3D::new(0.0, 0.0, -20.0),
+        radius: 10.0,
+        phong_data: Some(PhongModel {
+            emission: Material::Color(LinSrgb::new(1.0, 1.0, 1.0)),
+            emission_strength: 1.0,
+            ..PhongModel::new()
+        }),
+    }));
+    for x in [0.0, 0.9] {
+        let ray = Ray {
+            origin: Vec3D::new(x, 0.0, 5.0),
+            direction: Vec3D::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let distance = 2.0 * f64::sqrt(1.5 * 1.5 - x * x);
+        let color = scene.trace(ray, 0, &mut rng);
+        assert!(close_to(color, absorbed_over(distance)), "{:?}", color);
+    }
+}
+
+#[test]
+fn test_subsurface_walk() {
+    // a white, closed sphere that scatters all light inside of it, lit from the camera's side
+    let translucent = Arc::new(Sphere {
+        center: Vec3D::default(),
+        radius: 1.0,
+        phong_data: Some(PhongModel {
+            material: Material::Color(LinSrgb::new(1.0, 1.0, 1.0)),
+            k_d: 1.0,
+            subsurface: Some(Subsurface {
+                albedo: LinSrgb::new(1.0, 1.0, 1.0),
+                mean_free_path: 0.1,
+                max_steps: 10000,
+            }),
+            ..PhongModel::new()
+        }),
+    });
+    // walks only end on the sphere itself, not on another object inside of it
+    let core = Arc::new(Sphere {
+        center: Vec3D::default(),
+        radius: 0.5,
+        phong_data: None,
+    });
+    let mut scene = Scene {
+        objects: vec![translucent],
+        light_sources: vec![Arc::new(PointLight {
+            position: Vec3D::new(0.0, 0.0, 5.0),
+            color: LinSrgb::new(1.0, 1.0, 1.0),
+            intensity: 10.0,
+        })],
+        max_depth: 1,
+        sky: None,
+        area_light_samples: 0,
+        volumes: vec![],
+    };
+    let ray = Ray {
+        origin: Vec3D::new(0.0, 0.3, 5.0),
+        direction: Vec3D::new(0.0, 0.0, -1.0),
+        time: 0.0,
+    };
+    let mut rng = SmallRng::seed_from_u64(0);
+    for _ in 0..2 {
+        let color = (0..100).fold(LinSrgb::new(0.0, 0.0, 0.0), |sum, _| {
+            sum + scene.trace(ray, 0, &mut rng)
+        }) / 100.0;
+        assert!(color.red.is_finite() && color.red > 0.01);
+        scene.objects.push(core.clone());
+    }
+}
+
+#[test]
+fn test_emitter_does_not_light_itself() {
+    // a glowing, otherwise diffuse sphere alone in the dark only shows its own emission
+    let scene = Scene {
+        objects: vec![Arc::new(Sphere {
+            center: Vec3D::default(),
+            radius: 1.0,
+            phong_data: Some(PhongModel {
+                material: Material::Color(LinSrgb::new(1.0, 1.0, 1.0)),
+                k_d: 1.0,
+                emission: Material::Color(LinSrgb::new(0.5, 0.25, 0.125)),
+                emission_strength: 1.0,
+                ..PhongModel::new()
+            }),
+        })],
+        light_sources: vec![],
+        max_depth: 2,
+        sky: None,
+        area_light_samples: 16,
+        volumes: vec![],
+    };
+    let mut rng = SmallRng::seed_from_u64(0);
+    for x in [0.0, 0.5, 0.99] {
+        let ray = Ray {
+            origin: Vec3D::new(x, 0.0, 5.0),
+            direction: Vec3D::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert_eq!(
+            scene.trace(ray, 0, &mut rng),
+            LinSrgb::new(0.5, 0.25, 0.125)
+        );
+    }
+}
+
+#[test]
+fn test_emitter_seen_through_glass() {
+    // area lights aren't sampled through refractions, so the refracted ray has to see the
+    // emitter behind the glass sphere
+    let scene = Scene {
+        objects: vec![
+            Arc::new(Sphere {
+                center: Vec3D::default(),
+                radius: 1.0,
+                phong_data: Some(PhongModel {
+                    material: Material::Color(LinSrgb::new(1.0, 1.0, 1.0)),
+                    k_t: 1.0,
+                    ior: 1.5,
+                    ..PhongModel::new()
+                }),
+            }),
+            Arc::new(Sphere {
+                center: Vec3D::new(0.0, 0.0, -20.0),
+                radius: 10.0,
+                phong_data: Some(PhongModel {
+                    emission: Material::Color(LinSrgb::new(0.5, 0.25, 0.125)),
+                    emission_strength: 1.0,
+                    ..PhongModel::new()
+                }),
+            }),
+        ],
+        light_sources: vec![],
+        max_depth: 4,
+        sky: None,
+        area_light_samples: 16,
+        volumes: vec![],
+    };
+    let mut rng = SmallRng::seed_from_u64(0);
+    let ray = Ray {
+        origin: Vec3D::new(0.0, 0.0, 5.0),
+        direction: Vec3D::new(0.0, 0.0, -1.0),
+        time: 0.0,
+    };
+    let color = scene.trace(ray, 0, &mut rng);
+    assert!((color.red - 0.5).abs() < 1e-4, "{:?}", color);
+    assert!((color.blue - 0.125).abs() < 1e-4, "{:?}", color);
+}
@@ -0,0 +1,119 @@
+use simple_raytracer::math::vector::Vec3D;
+use simple_raytracer::media::*;
+use simple_raytracer::objects::{ray::Ray, sphere::Sphere};
+use simple_raytracer::scene::Scene;
+
+use palette::LinSrgb;
+use rand::{rngs::SmallRng, SeedableRng};
+use std::sync::Arc;
+
+#[test]
+fn test_homogeneous_transmittance() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let volume = Volume {
+        medium: Medium::homogeneous(0.5, 0.5, LinSrgb::new(1.0, 1.0, 1.0), 0.0),
+        boundary: Some(Arc::new(Sphere {
+            center: Vec3D::default(),
+            radius: 1.0,
+            phong_data: None,
+        })),
+    };
+    // the ray passes the unit sphere along a distance of 2
+    let ray = Ray {
+        origin: Vec3D::new(0.0, 0.0, -5.0),
+        direction: Vec3D::new(0.0, 0.0, 1.0),
+        time: 0.0,
+    };
+    let transmittance = volume.transmittance(ray, 0.0, f64::INFINITY, &mut rng);
+    assert!((transmittance - f64::exp(-2.0)).abs() < 1e-9);
+
+    // a ray starting inside of the sphere only passes a distance of 1
+    let ray = Ray {
+        origin: Vec3D::default(),
+        direction: Vec3D::new(0.0, 1.0, 0.0),
+        time: 0.0,
+    };
+    let transmittance = volume.transmittance(ray, 0.0, f64::INFINITY, &mut rng);
+    assert!((transmittance - f64::exp(-1.0)).abs() < 1e-9);
+
+    // rays missing the boundary are not attenuated
+    let ray = Ray {
+        origin: Vec3D::new(0.0, 2.0, -5.0),
+        direction: Vec3D::new(0.0, 0.0, 1.0),
+        time: 0.0,
+    };
+    assert_eq!(volume.transmittance(ray, 0.0, f64::INFINITY, &mut rng), 1.0);
+    assert!(volume
+        .sample_collision(ray, 0.0, f64::INFINITY, &mut rng)
+        .is_none());
+}
+
+#[test]
+fn test_density_grid() {
+    let grid = DensityGrid::from_fn(
+        Vec3D::default(),
+        Vec3D::new(1.0, 1.0, 1.0),
+        (4, 4, 4),
+        |p| p.x,
+    );
+    // linear functions are reproduced between the voxel centers
+    assert!((grid.lookup(Vec3D::new(0.5, 0.3, 0.7)) - 0.5).abs() < 1e-9);
+    assert!((grid.lookup(Vec3D::new(0.625, 0.5, 0.5)) - 0.625).abs() < 1e-9);
+    assert_eq!(grid.lookup(Vec3D::new(2.0, 0.5, 0.5)), 0.0);
+}
+
+#[test]
+fn test_unbounded_grid_volume() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    // a slab of fog that absorbs everything, filling the grid of a volume without boundary
+    let volume = Volume {
+        medium: Medium {
+            density: Density::Grid(DensityGrid::from_fn(
+                Vec3D::new(-1.0, -1.0, -1.0),
+                Vec3D::new(1.0, 1.0, 1.0),
+                (2, 2, 2),
+                |_| 1.0,
+            )),
+            ..Medium::homogeneous(1000.0, 0.0, LinSrgb::new(1.0, 1.0, 1.0), 0.0)
+        },
+        boundary: None,
+    };
+    let ray = |y: f64| Ray {
+        origin: Vec3D::new(0.0, y, -5.0),
+        direction: Vec3D::new(0.0, 0.0, 1.0),
+        time: 0.0,
+    };
+    // rays crossing the grid are blocked, rays passing it escape to infinity unattenuated
+    assert_eq!(
+        volume.transmittance(ray(0.0), 0.0, f64::INFINITY, &mut rng),
+        0.0
+    );
+    assert_eq!(
+        volume.transmittance(ray(2.0), 0.0, f64::INFINITY, &mut rng),
+        1.0
+    );
+    assert!(volume
+        .sample_collision(ray(2.0), 0.0, f64::INFINITY, &mut rng)
+        .is_none());
+
+    let scene = Scene {
+        objects: vec![],
+        light_sources: vec![],
+        max_depth: 4,
+        sky: None,
+        area_light_samples: 0,
+        volumes: vec![volume],
+    };
+    for y in [0.0, 2.0] {
+        let color = scene.trace(ray(y), 0, &mut rng);
+        assert_eq!(color, LinSrgb::new(0.0, 0.0, 0.0));
+    }
+}
+
+#[test]
+fn test_henyey_greenstein() {
+    let forward = Medium::homogeneous(0.0, 1.0, LinSrgb::new(1.0, 1.0, 1.0), 0.7);
+    assert!(forward.phase(1.0) > forward.phase(-1.0));
+    let isotropic = Medium::homogeneous(0.0, 1.0, LinSrgb::new(1.0, 1.0, 1.0), 0.0);
+    assert!((isotropic.phase(0.3) - 1.0 / (4.0 * std::f64::consts::PI)).abs() < 1e-12);
+}
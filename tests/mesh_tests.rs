@@ -0,0 +1,199 @@
+use simple_raytracer::math::vector::Vec3D;
+use simple_raytracer::objects::hittables::Hittable;
+use simple_raytracer::objects::mesh::*;
+use simple_raytracer::objects::ray::Ray;
+
+// Unit cube around the origin as an OBJ file without normals. With separate_faces every
+// face gets its own, slightly displaced copies of the corners, like in exported scans.
+fn cube_obj(name: &str, separate_faces: bool) -> std::path::PathBuf {
+    let corner = |i: usize| {
+        format!(
+            "v {} {} {}\n",
+            if i & 1 == 0 { -0.5 } else { 0.5 },
+            if i & 2 == 0 { -0.5 } else { 0.5 },
+            if i & 4 == 0 { -0.5 } else { 0.5 },
+        )
+    };
+    let faces = [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+    ];
+    let mut obj = String::new();
+    if separate_faces {
+        for (f, face) in faces.iter().enumerate() {
+            for &i in face {
+                let jitter = 1e-9 * f as f64;
+                obj += &corner(i).replace(" 0.5", &format!(" {}", 0.5 + jitter));
+            }
+        }
+        for f in 0..6 {
+            let i = 4 * f + 1;
+            obj += &format!("f {} {} {} {}\n", i, i + 1, i + 2, i + 3);
+        }
+    } else {
+        (0..8).for_each(|i| obj += &corner(i));
+        for face in faces {
+            obj += &format!(
+                "f {} {} {} {}\n",
+                face[0] + 1,
+                face[1] + 1,
+                face[2] + 1,
+                face[3] + 1
+            );
+        }
+    }
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, obj).unwrap();
+    path
+}
+
+// Shading normal where a ray towards the center of the cube enters it near a corner
+fn normal_near_corner(mesh: &Mesh) -> Vec3D {
+    let ray = Ray {
+        origin: Vec3D::new(0.49, 0.49, 5.0),
+        direction: Vec3D::new(0.0, 0.0, -1.0),
+        time: 0.0,
+    };
+    mesh.intersect(ray, 0.0, f64::INFINITY).unwrap().normal
+}
+
+#[test]
+fn test_generate_normals() {
+    let mut mesh = Mesh::from_file(cube_obj("simple_raytracer_cube.obj", false)).unwrap();
+    assert!(mesh.triangles().all(|triangle| triangle.normal.is_none()));
+
+    // below the crease angle of 90 degrees the faces stay flat
+    mesh.generate_normals(NormalWeighting::Angle, f64::to_radians(60.0));
+    assert!(normal_near_corner(&mesh).almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+
+    // with every face contributing its 90 degree corner the normal points along the diagonal
+    mesh.generate_normals(NormalWeighting::Angle, f64::to_radians(120.0));
+    let diagonal = Vec3D::new(1.0, 1.0, 1.0).unit_vector();
+    for triangle in mesh.triangles() {
+        for (p, n) in [triangle.vert_a, triangle.vert_b, triangle.vert_c]
+            .into_iter()
+            .zip(triangle.normal.unwrap())
+        {
+            assert!(n.almost_equal(p.unit_vector()));
+        }
+    }
+    assert!(normal_near_corner(&mesh).unit_vector() * diagonal > 0.9);
+
+    // area weights depend on how the faces were split into triangles
+    mesh.generate_normals(NormalWeighting::Area, f64::to_radians(120.0));
+    let normals: Vec<Vec3D> = mesh
+        .triangles()
+        .flat_map(|triangle| triangle.normal.unwrap())
+        .collect();
+    assert!(normals.iter().any(|n| n.x.abs() != n.z.abs()));
+}
+
+#[test]
+fn test_weld_vertices() {
+    let mut mesh = Mesh::from_file(cube_obj("simple_raytracer_cube_faces.obj", true)).unwrap();
+    assert_eq!(mesh.triangle_count(), 12);
+    // the copies of the corners don't touch, so the faces can't share normals
+    mesh.generate_normals(NormalWeighting::Angle, f64::to_radians(120.0));
+    assert!(normal_near_corner(&mesh).almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+
+    // a tolerance this small puts all coordinates outside of the representable cells
+    mesh.weld_vertices(1e-300);
+    assert_eq!(mesh.triangle_count(), 12);
+    mesh.weld_vertices(1e-6);
+    assert_eq!(mesh.triangle_count(), 12);
+    let corners: std::collections::HashSet<[u64; 3]> = mesh
+        .triangles()
+        .flat_map(|triangle| [triangle.vert_a, triangle.vert_b, triangle.vert_c])
+        .map(|p| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()])
+        .collect();
+    assert_eq!(corners.len(), 8);
+    mesh.generate_normals(NormalWeighting::Angle, f64::to_radians(120.0));
+    let diagonal = Vec3D::new(1.0, 1.0, 1.0).unit_vector();
+    assert!(normal_near_corner(&mesh).unit_vector() * diagonal > 0.9);
+
+    // welding with a large tolerance collapses the whole cube
+    mesh.weld_vertices(2.0);
+    assert!(mesh.triangle_count() == 0);
+}
+
+#[test]
+fn test_indexed_mesh() {
+    let mut mesh = Mesh::from_file(cube_obj("simple_raytracer_cube_indexed.obj", false)).unwrap();
+    // the corners are shared by all faces
+    assert_eq!((mesh.vertex_count(), mesh.triangle_count()), (8, 12));
+    let rebuilt = Mesh::new(mesh.triangles().collect());
+    assert_eq!((rebuilt.vertex_count(), rebuilt.triangle_count()), (8, 12));
+    let triangle = mesh.triangle(0);
+    assert_eq!(triangle.vert_a, Vec3D::new(-0.5, -0.5, -0.5));
+    assert!(triangle.normal.is_none() && triangle.uv.is_none());
+
+    // smooth normals only split the vertices along creases
+    mesh.generate_normals(NormalWeighting::Angle, f64::to_radians(60.0));
+    assert_eq!(mesh.vertex_count(), 24);
+    mesh.generate_normals(NormalWeighting::Angle, f64::to_radians(120.0));
+    assert_eq!(mesh.vertex_count(), 8);
+
+    let double_precision = normal_near_corner(&mesh);
+    mesh.set_single_precision();
+    assert!(mesh.positions.is_single_precision() && mesh.normals.is_single_precision());
+    assert!((normal_near_corner(&mesh) - double_precision).norm() < 1e-6);
+    // edits keep the precision
+    mesh.weld_vertices(1e-6);
+    assert!(mesh.positions.is_single_precision());
+
+    // a file where only some faces have normals or texture coordinates
+    let path = std::env::temp_dir().join("simple_raytracer_mixed.obj");
+    std::fs::write(
+        &path,
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvt 0.5 0.5\nvn 0 0 1\n\
+         f 1/1/1 2/1/1 3/1/1\nf 2 4 3\n",
+    )
+    .unwrap();
+    let mixed = Mesh::from_file(&path).unwrap();
+    assert_eq!(mixed.triangle_count(), 2);
+    let filled = mixed.triangle(1);
+    assert!(filled.normal.unwrap()[0].almost_equal(Vec3D::new(0.0, 0.0, 1.0)));
+    let (u, v) = filled.interpolate_uv(0.2, 0.3);
+    assert!((u - 0.2).abs() < 1e-12 && (v - 0.3).abs() < 1e-12);
+    // loading straight into single precision gives the same mesh as converting afterwards
+    let mut converted = Mesh::from_file(&path).unwrap();
+    converted.set_single_precision();
+    let single = Mesh::from_file_single_precision(&path).unwrap();
+    assert!(single.positions.is_single_precision() && single.uvs.is_single_precision());
+    assert_eq!(single.indices, converted.indices);
+    for i in 0..converted.vertex_count() as u32 {
+        assert_eq!(single.positions.get(i), converted.positions.get(i));
+        assert_eq!(single.normals.get(i), converted.normals.get(i));
+        assert_eq!(single.uvs.get(i), converted.uvs.get(i));
+    }
+}
+
+#[test]
+fn test_invalid_obj() {
+    let path = std::env::temp_dir().join("simple_raytracer_invalid.obj");
+    let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\n";
+    // indices counting from 0, relative indices and ones past the defined elements
+    for face in [
+        "f 0 1 2",
+        "f -3 -2 -1",
+        "f 1 2 4",
+        "f 1/2 2/1 3/1",
+        "f 1//1 2 3",
+        "f 1 2",
+    ] {
+        std::fs::write(&path, format!("{vertices}{face}\n")).unwrap();
+        let error = Mesh::from_file(&path).err().unwrap();
+        assert!(error.to_string().starts_with("line 5: "), "{}", error);
+    }
+    std::fs::write(&path, format!("{vertices}f 1/1 2 3/\n")).unwrap();
+    assert_eq!(Mesh::from_file(&path).unwrap().triangle_count(), 1);
+    // read errors are returned instead of skipping the line
+    std::fs::write(&path, b"v 0 0 0\nv \xff 0 0\n").unwrap();
+    let error = Mesh::from_file(&path).err().unwrap();
+    assert!(error.to_string().starts_with("line 2: "), "{}", error);
+    assert!(Mesh::from_file(std::env::temp_dir()).is_err());
+}
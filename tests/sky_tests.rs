@@ -0,0 +1,35 @@
+use simple_raytracer::math::vector::Vec3D;
+use simple_raytracer::sky::{PhysicalSky, SunPosition};
+
+#[test]
+fn test_sun_direction() {
+    // Sun at the horizon in the east
+    let sun = SunPosition::from_angles(0.0, 90.0);
+    assert!(sun.direction().almost_equal(Vec3D::new(1.0, 0.0, 0.0)));
+
+    // Equinox noon on the equator puts the sun (almost) in zenith
+    let sun = SunPosition::from_date_time(80, 12.0, 0.0);
+    assert!(sun.direction().y > 0.99);
+
+    // Morning sun is in the east, afternoon sun in the west
+    let morning = SunPosition::from_date_time(172, 9.0, 50.0);
+    let afternoon = SunPosition::from_date_time(172, 15.0, 50.0);
+    assert!(morning.direction().x > 0.0);
+    assert!(afternoon.direction().x < 0.0);
+    assert!((morning.elevation - afternoon.elevation).abs() < 1e-9);
+}
+
+#[test]
+fn test_sky_radiance() {
+    let sky = PhysicalSky::from_elevation_azimuth(30.0, 0.0, 3.0);
+    let zenith = sky.radiance(Vec3D::new(0.0, 1.0, 0.0));
+    // a clear sky is blue
+    assert!(zenith.blue > zenith.red);
+    // the sky is brighter around the sun than opposite of it
+    let towards_sun = sky.radiance(Vec3D::new(0.0, 0.6, 1.0));
+    let away_from_sun = sky.radiance(Vec3D::new(0.0, 0.6, -1.0));
+    assert!(towards_sun.green > away_from_sun.green);
+    // the low sun is reddened by the atmosphere
+    let sun = sky.sun_color();
+    assert!(sun.red > sun.blue);
+}
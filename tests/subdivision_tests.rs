@@ -0,0 +1,164 @@
+use simple_raytracer::math::vector::Vec3D;
+use simple_raytracer::objects::hittables::Hittable;
+use simple_raytracer::objects::ray::Ray;
+use simple_raytracer::objects::subdivision::*;
+
+// Unit cube around the origin with outward facing quads
+fn cube() -> PolygonMesh {
+    let vertices = (0..8)
+        .map(|i| {
+            Vec3D::new(
+                if i & 1 == 0 { -0.5 } else { 0.5 },
+                if i & 2 == 0 { -0.5 } else { 0.5 },
+                if i & 4 == 0 { -0.5 } else { 0.5 },
+            )
+        })
+        .collect();
+    let faces = vec![
+        vec![0, 2, 3, 1],
+        vec![4, 5, 7, 6],
+        vec![0, 1, 5, 4],
+        vec![2, 6, 7, 3],
+        vec![0, 4, 6, 2],
+        vec![1, 3, 7, 5],
+    ];
+    PolygonMesh {
+        vertices,
+        faces,
+        ..PolygonMesh::default()
+    }
+}
+
+#[test]
+fn test_catmull_clark() {
+    let cube = cube();
+    let once = cube.subdivide(SubdivisionScheme::CatmullClark);
+    // one vertex per original vertex, edge and face, four quads per face
+    assert_eq!(once.vertices.len(), 8 + 12 + 6);
+    assert_eq!(once.faces.len(), 24);
+    assert!(once.faces.iter().all(|face| face.len() == 4));
+    // corners are pulled in to (Q + 2R + (n - 3)S) / n = 5/18 per coordinate
+    assert!(once.vertices[7].almost_equal(Vec3D::new(5.0 / 18.0, 5.0 / 18.0, 5.0 / 18.0)));
+
+    // the surface shrinks towards a sphere and stays closed
+    let mesh = cube
+        .subdivide_levels(SubdivisionScheme::CatmullClark, 3)
+        .to_mesh();
+    let ray = Ray {
+        origin: Vec3D::new(0.01, 0.02, 5.0),
+        direction: Vec3D::new(0.0, 0.0, -1.0),
+        time: 0.0,
+    };
+    let hit = mesh.intersect(ray, 0.0, f64::INFINITY).unwrap();
+    assert!(hit.t > 4.5 && hit.t < 4.7);
+    assert!((hit.normal - Vec3D::new(0.0, 0.0, 1.0)).norm() < 0.05);
+
+    // with every edge creased the cube keeps its corners
+    let mut creased = cube.clone();
+    for face in cube.faces.iter() {
+        for i in 0..4 {
+            creased.set_crease(face[i], face[(i + 1) % 4], 10.0);
+        }
+    }
+    let creased = creased.subdivide_levels(SubdivisionScheme::CatmullClark, 2);
+    assert!(creased.vertices[7].almost_equal(Vec3D::new(0.5, 0.5, 0.5)));
+    // edges stay straight while the face centers stay flat
+    assert!(creased
+        .vertices
+        .iter()
+        .all(|v| v.x.abs().max(v.y.abs()).max(v.z.abs()) > 0.5 - 1e-12));
+}
+
+#[test]
+fn test_loop_subdivision() {
+    // a single triangle only has boundary edges, its corners stay and its edges are split
+    let triangle = PolygonMesh {
+        vertices: vec![
+            Vec3D::new(0.0, 0.0, 0.0),
+            Vec3D::new(1.0, 0.0, 0.0),
+            Vec3D::new(0.0, 1.0, 0.0),
+        ],
+        faces: vec![vec![0, 1, 2]],
+        ..PolygonMesh::default()
+    };
+    let once = triangle.subdivide(SubdivisionScheme::Loop);
+    assert_eq!(once.faces.len(), 4);
+    assert_eq!(&once.vertices[..3], &triangle.vertices[..]);
+    assert!(once.vertices.contains(&Vec3D::new(0.5, 0.5, 0.0)));
+
+    // faces repeating a vertex are dropped, the vertex only they use stays in place
+    let degenerate = PolygonMesh {
+        vertices: vec![
+            Vec3D::new(0.0, 0.0, 0.0),
+            Vec3D::new(1.0, 0.0, 0.0),
+            Vec3D::new(0.0, 1.0, 0.0),
+            Vec3D::new(1.0, 1.0, 0.0),
+        ],
+        faces: vec![vec![0, 1, 2], vec![1, 1, 2], vec![3, 3, 3]],
+        ..PolygonMesh::default()
+    };
+    let once = degenerate.subdivide(SubdivisionScheme::Loop);
+    assert_eq!(once.faces.len(), 4);
+    assert_eq!(&once.vertices[..4], &degenerate.vertices[..]);
+
+    // the quads of the cube are split into triangles first
+    let cube = cube().subdivide(SubdivisionScheme::Loop);
+    assert_eq!(cube.faces.len(), 4 * 12);
+    assert_eq!(cube.vertices.len(), 8 + 18);
+    // the corners are rounded off
+    assert!(cube.vertices[..8]
+        .iter()
+        .all(|v| v.x.abs().max(v.y.abs()).max(v.z.abs()) < 0.5));
+}
+
+#[test]
+fn test_polygon_mesh_from_obj() {
+    // faces keep their polygons and only their position indices
+    let path = std::env::temp_dir().join("simple_raytracer_polygons.obj");
+    std::fs::write(
+        &path,
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 2 0 0\nvt 0 0\nvn 0 0 1\n\
+         f 1/1/1 2/1/1 3/1/1 4/1/1\nf 2//1 5//1 3//1\n",
+    )
+    .unwrap();
+    let mesh = PolygonMesh::from_obj(&path).unwrap();
+    assert_eq!(mesh.vertices.len(), 5);
+    assert_eq!(mesh.faces, [vec![0, 1, 2, 3], vec![1, 4, 2]]);
+    // corners without texture coordinates share an extra one at (0, 0)
+    assert_eq!(mesh.face_uvs, [vec![0, 0, 0, 0], vec![1, 1, 1]]);
+    assert_eq!(mesh.uvs, [(0.0, 0.0), (0.0, 0.0)]);
+
+    std::fs::write(&path, "v 0 0 0\nv 1 0 0\nf 1 2\n").unwrap();
+    assert!(PolygonMesh::from_obj(&path).is_err());
+}
+
+#[test]
+fn test_subdivided_uvs() {
+    // two quads side by side with a texture seam at the shared edge
+    let path = std::env::temp_dir().join("simple_raytracer_textured_quads.obj");
+    std::fs::write(
+        &path,
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 2 0 0\nv 2 1 0\n\
+         vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvt 0.5 0\nvt 0.5 1\n\
+         f 1/1 2/2 3/3 4/4\nf 2/5 5/2 6/3 3/6\n",
+    )
+    .unwrap();
+    let mesh = PolygonMesh::from_obj(&path).unwrap();
+    let once = mesh.subdivide(SubdivisionScheme::CatmullClark);
+    assert_eq!(once.face_uvs.len(), once.faces.len());
+    // the uvs of the face points are the centers of the faces' uvs
+    assert!(once.uvs.contains(&(0.5, 0.5)));
+    assert!(once.uvs.contains(&(0.75, 0.5)));
+
+    // the vertices on the seam are split in the triangle mesh, the others shared
+    let triangles = load_subdivided(&path, SubdivisionScheme::Loop, 2).unwrap();
+    let twice = mesh.subdivide_levels(SubdivisionScheme::Loop, 2);
+    let seam = twice.vertices.iter().filter(|v| v.x == 1.0).count();
+    assert_eq!(triangles.vertex_count(), twice.vertices.len() + seam);
+    for triangle in triangles.triangles() {
+        let [a, b, c] = triangle.uv.unwrap();
+        let x = (triangle.vert_a.x + triangle.vert_b.x + triangle.vert_c.x) / 3.0;
+        let u = (a.0 + b.0 + c.0) / 3.0;
+        assert!(if x < 1.0 { u < 1.0 } else { u >= 0.5 });
+    }
+}
@@ -102,3 +102,22 @@ fn test_reflection() {
         b.reflect(n)
     ));
 }
+
+#[test]
+fn test_refraction() {
+    let n = Vec3D::new(0.0, 1.0, 0.0);
+    // perpendicular incidence passes straight through
+    let down = Vec3D::new(0.0, -1.0, 0.0);
+    assert!(down.refract(n, 1.0 / 1.5).unwrap().almost_equal(down));
+
+    // Snell's law: sin(theta_t) = eta * sin(theta_i)
+    let a = Vec3D::new(1.0, -1.0, 0.0).unit_vector();
+    let eta = 1.0 / 1.5;
+    let t = a.refract(n, eta).unwrap();
+    assert!((t.norm() - 1.0).abs() < 1e-12);
+    assert!((t.x - eta * a.x).abs() < 1e-12);
+
+    // total internal reflection when leaving a dense medium at a grazing angle
+    let b = Vec3D::new(1.0, -0.2, 0.0).unit_vector();
+    assert!(b.refract(n, 1.5).is_none());
+}